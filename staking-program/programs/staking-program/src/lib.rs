@@ -9,6 +9,10 @@ use anchor_spl::token_interface::{
     TokenInterface,
     TransferChecked,
     transfer_checked,
+    CloseAccount,
+    close_account,
+    Burn,
+    burn,
 };
 use anchor_lang::solana_program::program::invoke_signed;
 use anchor_lang::solana_program::system_instruction;
@@ -18,18 +22,220 @@ declare_id!("8PQxN4ArNB8vZUNT8RiuGTGSDMHkPWAHFa75JGZVppij");
 
 const SECONDS_PER_YEAR: u64 = 31_536_000; // 365 days
 
+// Role bits stored in RoleRegistry members
+pub const ROLE_PAUSER: u8 = 1 << 0;
+pub const ROLE_FEE_MANAGER: u8 = 1 << 1;
+pub const ROLE_REWARD_FUNDER: u8 = 1 << 2;
+const MAX_ROLE_MEMBERS: usize = 8;
+// Roles each registry kind can hand out
+const PLATFORM_ROLES: u8 = ROLE_PAUSER | ROLE_FEE_MANAGER;
+const PROJECT_ROLES: u8 = ROLE_PAUSER | ROLE_REWARD_FUNDER;
+
+// Pause flag bits - set_pause_flags uses deposit/withdraw/claim,
+// Platform.global_pause additionally uses reflections
+pub const PAUSE_DEPOSIT: u8 = 1 << 0;
+pub const PAUSE_WITHDRAW: u8 = 1 << 1;
+pub const PAUSE_CLAIM: u8 = 1 << 2;
+pub const PAUSE_REFLECTIONS: u8 = 1 << 3;
+
+// Hard protocol caps on platform fees
+pub const MAX_PLATFORM_TOKEN_FEE_BPS: u64 = 1_000; // 10%
+pub const MAX_PLATFORM_SOL_FEE: u64 = 100_000_000; // 0.1 SOL
+// Delay between queueing a fee change and it becoming applicable
+pub const FEE_CHANGE_DELAY_SECONDS: i64 = 172_800; // 48 hours
+
+// Co-incentive reward streams per project and accumulator scaling
+pub const MAX_REWARD_STREAMS: usize = 3;
+const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+// Lock tiers: reward weight multipliers in bps of the staked amount
+pub const MAX_LOCK_TIERS: usize = 4;
+pub const BASE_MULTIPLIER_BPS: u64 = 10_000; // 1x
+pub const MAX_LOCK_MULTIPLIER_BPS: u64 = 100_000; // 10x
+
+// Lock modes: how top-ups are locked
+pub const LOCK_MODE_RELOCK: u8 = 0; // every deposit re-locks the whole stake
+pub const LOCK_MODE_LOTS: u8 = 1; // each deposit unlocks on its own, withdrawn FIFO
+pub const LOCK_MODE_WEIGHTED_AVERAGE: u8 = 2; // one unlock time, averaged by amount
+pub const MAX_STAKE_LOTS: usize = 8;
+
+// Early exit penalty destinations
+pub const PENALTY_TO_BURN: u8 = 0;
+pub const PENALTY_TO_REWARD_VAULT: u8 = 1;
+pub const PENALTY_TO_STAKERS: u8 = 2;
+pub const PENALTY_TO_FEE_COLLECTOR: u8 = 3;
+pub const MAX_EARLY_EXIT_PENALTY_BPS: u64 = 5_000; // 50%
+
+// Longest cooldown set_unbonding_period accepts
+pub const MAX_UNBONDING_SECONDS: u64 = 2_592_000; // 30 days
+
 // ✅ NEW: Helper to check if a mint is Native SOL
 fn is_native_sol(mint: &Pubkey) -> bool {
     mint.to_string() == "So11111111111111111111111111111111111111112"
 }
 
+// Returns true if `member` holds `role` in the (optional) role registry
+fn has_role(registry: Option<&RoleRegistry>, member: &Pubkey, role: u8) -> bool {
+    registry.is_some_and(|registry| registry.has_role(member, role))
+}
+
+// A grant must name exactly one role that the registry kind hands out
+fn require_grantable_role(role: u8, grantable: u8) -> Result<()> {
+    require!(
+        role.is_power_of_two() && role & grantable != 0,
+        ErrorCode::InvalidRole
+    );
+    Ok(())
+}
+
+// Sets or clears the project pause flags in `flags`; only the admin may clear them
+fn apply_pause_flags(project: &mut Project, flags: u8, paused: bool, is_admin: bool) -> Result<()> {
+    require!(
+        flags != 0 && flags & !(PAUSE_DEPOSIT | PAUSE_WITHDRAW | PAUSE_CLAIM) == 0,
+        ErrorCode::InvalidPauseFlags
+    );
+    require!(paused || is_admin, ErrorCode::Unauthorized);
+
+    if flags & PAUSE_DEPOSIT != 0 {
+        project.deposit_paused = paused;
+    }
+    if flags & PAUSE_WITHDRAW != 0 {
+        project.withdraw_paused = paused;
+    }
+    if flags & PAUSE_CLAIM != 0 {
+        project.claim_paused = paused;
+    }
+    Ok(())
+}
+
+// Next Platform.global_pause value; only the platform admin may clear flags
+fn next_global_pause(current: u8, flags: u8, is_admin: bool) -> Result<u8> {
+    require!(
+        flags & !(PAUSE_DEPOSIT | PAUSE_WITHDRAW | PAUSE_CLAIM | PAUSE_REFLECTIONS) == 0,
+        ErrorCode::InvalidPauseFlags
+    );
+    require!(current & !flags == 0 || is_admin, ErrorCode::Unauthorized);
+    Ok(flags)
+}
+
+// Platform-wide circuit breaker, checked alongside the per-project pause flags
+fn require_not_globally_paused(platform: &Platform, flag: u8) -> Result<()> {
+    require!(platform.global_pause & flag == 0, ErrorCode::GloballyPaused);
+    Ok(())
+}
+
+// Deserializes an SPL/Token-2022 token account, checking it is owned by `token_program`
+fn read_token_account(account: &AccountInfo, token_program: &Pubkey) -> Result<TokenAccount> {
+    require!(account.owner == token_program, ErrorCode::InvalidTokenProgram);
+    let data = account.try_borrow_data()?;
+    TokenAccount::try_deserialize(&mut &data[..])
+}
+
+// Balance held by a project vault - lamports above rent for Native SOL, token amount otherwise
+fn vault_balance(vault: &AccountInfo, is_native: bool) -> Result<u64> {
+    if is_native {
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(vault.data_len());
+        Ok(vault.lamports().saturating_sub(rent_exempt_minimum))
+    } else {
+        let vault_data = vault.try_borrow_data()?;
+        require!(vault_data.len() >= 72, ErrorCode::InvalidTokenAccountData);
+        Ok(u64::from_le_bytes(vault_data[64..72].try_into().unwrap()))
+    }
+}
+
+// Validates a token account's owning program, mint and authority (SPL/Token-2022)
+fn validate_token_account(
+    account: &AccountInfo,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+) -> Result<()> {
+    let token_account = read_token_account(account, token_program)?;
+    require!(token_account.mint == *mint, ErrorCode::TokenAccountMintMismatch);
+    require!(token_account.owner == *authority, ErrorCode::TokenAccountAuthorityMismatch);
+    Ok(())
+}
+
+// Validates a wallet-side account: the wallet itself for Native SOL,
+// otherwise a token account of `mint` owned by the wallet
+fn validate_wallet_account(
+    account: &AccountInfo,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+    wallet: &Pubkey,
+) -> Result<()> {
+    if is_native_sol(mint) {
+        require!(account.key() == *wallet, ErrorCode::NativeWalletMismatch);
+        Ok(())
+    } else {
+        validate_token_account(account, token_program, mint, wallet)
+    }
+}
+
+// Validates a payout destination: the stake's withdrawal wallet for Native SOL,
+// otherwise a token account of `mint` owned by the withdrawal wallet
+fn validate_withdrawal_account(
+    account: &AccountInfo,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+    stake: &Stake,
+) -> Result<()> {
+    if is_native_sol(mint) {
+        require!(
+            account.key() == stake.withdrawal_wallet,
+            ErrorCode::InvalidWithdrawalWallet
+        );
+    } else {
+        let token_account = read_token_account(account, token_program)?;
+        require!(token_account.mint == *mint, ErrorCode::TokenAccountMintMismatch);
+        require!(
+            token_account.owner == stake.withdrawal_wallet,
+            ErrorCode::InvalidWithdrawalWallet
+        );
+    }
+    Ok(())
+}
+
+// Validates an optional reflection vault against project.reflection_vault
+fn validate_reflection_vault(project: &Project, reflection_vault: Option<&AccountInfo>) -> Result<()> {
+    if let Some(vault) = reflection_vault {
+        require!(
+            project.reflection_vault == Some(vault.key()),
+            ErrorCode::InvalidReflectionVault
+        );
+    }
+    Ok(())
+}
+
+// Rejects users who could not pay the platform SOL fee and stay rent exempt
+fn require_sol_for_fee(user: &AccountInfo, platform_sol_fee: u64) -> Result<()> {
+    let rent_minimum = Rent::get()?.minimum_balance(0);
+    require!(
+        user.lamports() >= platform_sol_fee.saturating_add(rent_minimum),
+        ErrorCode::InsufficientSolForFee
+    );
+    Ok(())
+}
+
+// Seeds a project PDA signs CPIs with; bind the result to a local before use
+macro_rules! project_signer_seeds {
+    ($project:expr) => {
+        &[
+            b"project".as_ref(),
+            $project.token_mint.as_ref(),
+            &$project.pool_id.to_le_bytes(),
+            &[$project.bump],
+        ]
+    };
+}
+
 fn transfer_tokens<'info>(
     from: AccountInfo<'info>,
     to: AccountInfo<'info>,
     authority: AccountInfo<'info>,
     mint: &InterfaceAccount<'info, Mint>,
     token_program: AccountInfo<'info>,
-    system_program: AccountInfo<'info>,
+    _system_program: AccountInfo<'info>,
     amount: u64,
     signer_seeds: Option<&[&[&[u8]]]>,
 ) -> Result<()> {
@@ -91,6 +297,8 @@ pub mod staking_program {
         platform_token_fee_bps: u64,
         platform_sol_fee: u64,
     ) -> Result<()> {
+        validate_fees(platform_token_fee_bps, platform_sol_fee)?;
+
         let platform = &mut ctx.accounts.platform;
         
         platform.admin = ctx.accounts.admin.key();
@@ -99,6 +307,8 @@ pub mod staking_program {
         platform.platform_sol_fee = platform_sol_fee;
         platform.is_initialized = true;
         platform.bump = ctx.bumps.platform;
+        platform.pending_admin = None;
+        platform.global_pause = 0;
         
         emit!(PlatformInitialized {
             admin: platform.admin,
@@ -127,6 +337,7 @@ pub mod staking_program {
         project.total_staked = 0;
         project.total_rewards_deposited = 0;
         project.total_rewards_claimed = 0;
+        project.total_rewards_accrued = 0;
         
         project.rate_bps_per_year = 0;
         project.rate_mode = 0;
@@ -137,7 +348,6 @@ pub mod staking_program {
         project.pool_end_time = 0;
         
         project.last_update_time = 0;
-        project.reward_per_token_stored = 0;
         project.reflection_per_token_stored = 0;
         project.last_reflection_update_time = 0;
         project.last_reflection_balance = 0;  // Initialize reflection balance tracker
@@ -153,6 +363,18 @@ pub mod staking_program {
         
         project.bump = ctx.bumps.project;
         project.total_reflection_debt = 0;  // ✅ ADD: Initialize debt tracking
+        project.pending_admin = None;
+        project.emergency_withdraw_enabled = false;
+        project.withdrawal_wallet_delay_seconds = 0;
+        project.compound_resets_lock = false;
+        
+        // Passing the staking mint as reward_mint keeps a single-mint pool
+        let reward_mint = ctx.accounts.reward_mint.key();
+        project.reward_mint = if reward_mint == token_mint { None } else { Some(reward_mint) };
+        project.token_decimals = ctx.accounts.token_mint.decimals;
+        project.reward_decimals = ctx.accounts.reward_mint.decimals;
+        project.reward_stream_count = 0;
+        project.acc_reward_per_token = 0;
         
         emit!(ProjectCreated {
             project: project.key(),
@@ -160,6 +382,7 @@ pub mod staking_program {
             staking_vault: ctx.accounts.staking_vault.key(),
             reward_vault: ctx.accounts.reward_vault.key(),
             admin: ctx.accounts.admin.key(),
+            reward_mint,
         });
         
         Ok(())
@@ -209,31 +432,22 @@ pub mod staking_program {
         project.rate_bps_per_year = params.rate_bps_per_year;
         project.lockup_seconds = params.lockup_seconds;
         project.pool_duration_seconds = params.pool_duration_seconds;
-        project.pool_start_time = current_time;
-        project.pool_end_time = current_time
+        
+        // Pools can be announced and funded ahead of a scheduled launch
+        let start_time = params.start_time.unwrap_or(current_time);
+        require!(start_time >= current_time, ErrorCode::InvalidStartTime);
+        project.pool_start_time = start_time;
+        project.pool_end_time = start_time
             .checked_add(params.pool_duration_seconds as i64)
             .ok_or(ErrorCode::MathOverflow)?;
+        project.allow_early_deposits = params.allow_early_deposits;
         
         project.last_update_time = current_time;
         project.last_reflection_update_time = current_time;
         
         // Calculate reward rate for fixed APY pools
         if params.rate_mode == 0 {
-            // Fixed APY pool - multiply by 1e9
-            let numerator = (params.rate_bps_per_year as u128)
-                .checked_mul(1_000_000_000u128)
-                .ok_or(ErrorCode::MathOverflow)?;
-            
-            let denominator = (10_000u128)
-                .checked_mul(31_536_000u128)
-                .ok_or(ErrorCode::MathOverflow)?;
-                
-            let rate_per_second = numerator
-                .checked_div(denominator)
-                .ok_or(ErrorCode::DivisionByZero)?;
-            
-            require!(rate_per_second <= u64::MAX as u128, ErrorCode::AprTooHigh);
-            project.reward_rate_per_second = rate_per_second as u64;
+            project.reward_rate_per_second = fixed_rate_per_second(params.rate_bps_per_year)?;
             
             msg!("Fixed APY pool rate: {}", project.reward_rate_per_second);
         }
@@ -244,6 +458,11 @@ pub mod staking_program {
             project.referrer_split_bps = params.referrer_split_bps.unwrap_or(0);
         }
         
+        validate_penalty_config(project, params.early_exit_penalty_bps, params.penalty_destination)?;
+        project.early_exit_penalty_bps = params.early_exit_penalty_bps;
+        project.early_exit_penalty_decays = params.early_exit_penalty_decays;
+        project.penalty_destination = params.penalty_destination;
+
         // ✅ HANDLE REFLECTIONS - ALL THREE TYPES (Native SOL, SPL, Self)
         project.enable_reflections = params.enable_reflections;
         
@@ -344,6 +563,8 @@ pub mod staking_program {
             lockup_seconds: params.lockup_seconds,
             pool_duration_seconds: params.pool_duration_seconds,
             reflections_enabled: params.enable_reflections,
+            pool_start_time: project.pool_start_time,
+            allow_early_deposits: project.allow_early_deposits,
         });
         
         msg!("✅ Pool initialized successfully");
@@ -356,41 +577,39 @@ pub mod staking_program {
 
     pub fn deposit<'info>(
         ctx: Context<'_, '_, '_, 'info, Deposit<'info>>,
-        token_mint: Pubkey,
-        pool_id: u64,
-        amount: u64
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        amount: u64,
+        lock_tier: u8,
     ) -> Result<()> {
         require!(amount > 0, ErrorCode::InvalidAmount);
+        ctx.accounts.validate()?;
+        let tier = ctx.accounts.project.lock_tier_terms(lock_tier)?;
         
         let platform_token_fee_bps = ctx.accounts.platform.platform_token_fee_bps;
         let platform_sol_fee = ctx.accounts.platform.platform_sol_fee;
-        let fee_collector = ctx.accounts.platform.fee_collector;
         
         let project_is_initialized = ctx.accounts.project.is_initialized;
         let project_is_paused = ctx.accounts.project.is_paused;
         let project_deposit_paused = ctx.accounts.project.deposit_paused;
         let project_pool_end_time = ctx.accounts.project.pool_end_time;
         let project_key = ctx.accounts.project.key();
-        let project_token_mint = ctx.accounts.project.token_mint;
-        let project_pool_id = ctx.accounts.project.pool_id;
-        let project_bump = ctx.accounts.project.bump;
-        let project_referrer = ctx.accounts.project.referrer;
-        let project_referrer_split_bps = ctx.accounts.project.referrer_split_bps;
-        let project_reward_per_token_stored = ctx.accounts.project.reward_per_token_stored;
         let project_reflection_per_token_stored = ctx.accounts.project.reflection_per_token_stored;
         
         require!(project_is_initialized, ErrorCode::NotInitialized);
         require!(!project_is_paused, ErrorCode::ProjectPaused);
         require!(!project_deposit_paused, ErrorCode::DepositsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_DEPOSIT)?;
         
-        let rent_minimum = 890880u64;
-        require!(
-            ctx.accounts.user.lamports() >= platform_sol_fee.saturating_add(rent_minimum),
-            ErrorCode::InsufficientSolForFee
-        );
+        require_sol_for_fee(&ctx.accounts.user, platform_sol_fee)?;
         
         let current_time = Clock::get()?.unix_timestamp;
         require!(current_time < project_pool_end_time, ErrorCode::PoolEnded);
+        // Early deposits, when allowed, only start earning at pool_start_time
+        require!(
+            current_time >= ctx.accounts.project.pool_start_time || ctx.accounts.project.allow_early_deposits,
+            ErrorCode::PoolNotStarted
+        );
         
         let token_fee = amount
             .checked_mul(platform_token_fee_bps)
@@ -442,16 +661,19 @@ pub mod staking_program {
         
         msg!("💰 Deposited {} tokens, vault received {} (after any transfer tax)", amount_after_fee, actual_received);
         
+        // Checkpoint reward streams against the pre-deposit amounts
+        let (fee_accounts, stream_accounts) = split_remaining_accounts(&ctx.accounts.project, ctx.remaining_accounts)?;
+        settle_reward_streams(&project_key, &ctx.accounts.project, &mut ctx.accounts.stake, stream_accounts)?;
+        
         let stake = &mut ctx.accounts.stake;
         let is_initialized = stake.bump != 0;
         
         if !is_initialized {
+    update_pool_reward(&mut ctx.accounts.project)?;
     stake.user = ctx.accounts.user.key();
     stake.project = project_key;
-    stake.amount = actual_received;  // ✅ Use actual amount received!
     stake.last_stake_timestamp = current_time;
     stake.withdrawal_wallet = ctx.accounts.user.key();
-    stake.reward_per_token_paid = project_reward_per_token_stored;
     stake.rewards_pending = 0;
     stake.total_rewards_claimed = 0;
     stake.reflection_per_token_paid = project_reflection_per_token_stored;
@@ -460,12 +682,14 @@ pub mod staking_program {
     stake.reflection_debt = 0;
     stake.reward_rate_snapshot = ctx.accounts.project.reward_rate_per_second;
     stake.bump = ctx.bumps.stake;
+    stake.pending_withdrawal_wallet = None;
+    stake.withdrawal_wallet_effective_time = 0;
+    stake.acc_reward_per_token_paid = ctx.accounts.project.acc_reward_per_token;
+    stake.last_reward_update = current_time;
+    stake.lock_tier = lock_tier;
     
     // ✅ Update project.total_staked with actual received amount
-    let project_mut = &mut ctx.accounts.project;
-    project_mut.total_staked = project_mut.total_staked
-        .checked_add(actual_received)  // ✅ Use actual_received!
-        .ok_or(ErrorCode::MathOverflow)?;
+    add_to_stake(&mut ctx.accounts.project, stake, actual_received)?;
 } else {
     require!(stake.user == ctx.accounts.user.key(), ErrorCode::Unauthorized);
     require!(stake.project == project_key, ErrorCode::InvalidProject);
@@ -484,17 +708,28 @@ pub mod staking_program {
         update_reflection(&mut ctx.accounts.project, stake, reflection_vault_account.as_ref())?;
     }
 
-    stake.amount = stake.amount
-        .checked_add(actual_received)  // ✅ Use actual_received!
-        .ok_or(ErrorCode::MathOverflow)?;
-    
-    let project_mut = &mut ctx.accounts.project;
-    project_mut.total_staked = project_mut.total_staked
-        .checked_add(actual_received)  // ✅ Use actual_received!
-        .ok_or(ErrorCode::MathOverflow)?;
+    // A top-up moves the whole position onto the current fixed rate
+    if let Some(old_rate) = reprice_stake(&mut ctx.accounts.project, stake)? {
+        emit!(StakeRateMigrated {
+            user: stake.user,
+            project: project_key,
+            old_rate,
+            new_rate: stake.reward_rate_snapshot,
+        });
+    }
+
+    // Switching tier re-weights the whole position; locks can only get longer
+    switch_lock_tier(&ctx.accounts.project, stake, lock_tier, current_time)?;
+
+    add_to_stake(&mut ctx.accounts.project, stake, actual_received)?;
     
     stake.last_stake_timestamp = current_time;
 }
+        let unlock_time = current_time
+            .checked_add(tier.lockup_seconds as i64)
+            .ok_or(ErrorCode::MathOverflow)?;
+        lock_deposit(&ctx.accounts.project, stake, actual_received, unlock_time)?;
+        require_rewards_funded(&ctx.accounts.project)?;
         
         // ✅ Transfer token fee to fee collector
         if token_fee > 0 {
@@ -510,79 +745,14 @@ pub mod staking_program {
             )?;
         }
         
-        if platform_sol_fee > 0 {
-            if let Some(referrer_key) = project_referrer {
-                // Get referrer from remaining_accounts if present
-                let referrer_account = if !ctx.remaining_accounts.is_empty() {
-                    Some(&ctx.remaining_accounts[0])
-                } else {
-                    None
-                };
-                
-                if let Some(referrer_account) = referrer_account {
-                    require!(
-                        referrer_account.key() == referrer_key,
-                        ErrorCode::InvalidReferrer
-                    );
-                    
-                    let referrer_amount = platform_sol_fee
-                        .checked_mul(project_referrer_split_bps)
-                        .ok_or(ErrorCode::MathOverflow)?
-                        .checked_div(10000)
-                        .ok_or(ErrorCode::MathOverflow)?;
-                    
-                    let admin_amount = platform_sol_fee
-                        .checked_sub(referrer_amount)
-                        .ok_or(ErrorCode::MathOverflow)?;
-                    
-                    system_program::transfer(
-                        CpiContext::new(
-                            ctx.accounts.system_program.to_account_info(),
-                            system_program::Transfer {
-                                from: ctx.accounts.user.to_account_info(),
-                                to: ctx.accounts.fee_collector.to_account_info(),
-                            },
-                        ),
-                        admin_amount,
-                    )?;
-                    
-                    if referrer_amount > 0 {
-                        system_program::transfer(
-                            CpiContext::new(
-                                ctx.accounts.system_program.to_account_info(),
-                                system_program::Transfer {
-                                    from: ctx.accounts.user.to_account_info(),
-                                    to: referrer_account.to_account_info(),
-                                },
-                            ),
-                            referrer_amount,
-                        )?;
-                    }
-                } else {
-                    system_program::transfer(
-                        CpiContext::new(
-                            ctx.accounts.system_program.to_account_info(),
-                            system_program::Transfer {
-                                from: ctx.accounts.user.to_account_info(),
-                                to: ctx.accounts.fee_collector.to_account_info(),
-                            },
-                        ),
-                        platform_sol_fee,
-                    )?;
-                }
-            } else {
-                system_program::transfer(
-                    CpiContext::new(
-                        ctx.accounts.system_program.to_account_info(),
-                        system_program::Transfer {
-                            from: ctx.accounts.user.to_account_info(),
-                            to: ctx.accounts.fee_collector.to_account_info(),
-                        },
-                    ),
-                    platform_sol_fee,
-                )?;
-            }
-        }
+        collect_sol_fee(
+            &ctx.accounts.project,
+            platform_sol_fee,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.fee_collector,
+            fee_accounts,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
         
         emit!(TokensDeposited {
             user: ctx.accounts.user.key(),
@@ -598,23 +768,22 @@ pub mod staking_program {
 
     pub fn withdraw<'info>(
         ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
-        token_mint: Pubkey,
-        pool_id: u64,
+        _token_mint: Pubkey,
+        _pool_id: u64,
         amount: u64
     ) -> Result<()> {
         require!(amount > 0, ErrorCode::InvalidAmount);
         require!(ctx.accounts.stake.amount >= amount, ErrorCode::InsufficientBalance);
+        ctx.accounts.validate()?;
         
         // ✅ Access directly - no local variable copies
         require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
         require!(!ctx.accounts.project.is_paused, ErrorCode::ProjectPaused);
         require!(!ctx.accounts.project.withdraw_paused, ErrorCode::WithdrawalsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_WITHDRAW)?;
+        require!(ctx.accounts.project.unbonding_seconds == 0, ErrorCode::UnbondingRequired);
         
-        let rent_minimum = 890880u64;
-        require!(
-            ctx.accounts.user.lamports() >= ctx.accounts.platform.platform_sol_fee.saturating_add(rent_minimum),
-            ErrorCode::InsufficientSolForFee
-        );
+        require_sol_for_fee(&ctx.accounts.user, ctx.accounts.platform.platform_sol_fee)?;
         
         let token_fee = amount
             .checked_mul(ctx.accounts.platform.platform_token_fee_bps)
@@ -636,37 +805,21 @@ pub mod staking_program {
         update_reflection(&mut ctx.accounts.project, &mut ctx.accounts.stake, ctx.accounts.reflection_vault.as_ref())?;
         
         let current_time = Clock::get()?.unix_timestamp;
-        let time_staked = current_time
-            .checked_sub(ctx.accounts.stake.last_stake_timestamp)
+        // Locked principal can only leave early by paying the pool's penalty
+        let penalty = early_exit_penalty(&ctx.accounts.project, &ctx.accounts.stake, amount, current_time)?;
+        let amount_to_user = amount_after_fee
+            .checked_sub(penalty)
             .ok_or(ErrorCode::MathOverflow)?;
         
-        require!(time_staked >= 0, ErrorCode::InvalidTimestamp);
-        
-        if time_staked < ctx.accounts.project.lockup_seconds as i64 {
-            return Err(ErrorCode::LockupNotExpired.into());
-        }
-        
-        // Update stake amount
-        ctx.accounts.stake.amount = ctx.accounts.stake.amount
-            .checked_sub(amount)
-            .ok_or(ErrorCode::MathOverflow)?;
+        let project_key = ctx.accounts.project.key();
+        let (fee_accounts, stream_accounts) = split_remaining_accounts(&ctx.accounts.project, ctx.remaining_accounts)?;
+        settle_reward_streams(&project_key, &ctx.accounts.project, &mut ctx.accounts.stake, stream_accounts)?;
         
-        // Update total staked
-        require!(
-            ctx.accounts.project.total_staked >= amount,
-            ErrorCode::InconsistentTotalStaked
-        );
-        ctx.accounts.project.total_staked = ctx.accounts.project.total_staked
-            .checked_sub(amount)
-            .ok_or(ErrorCode::MathOverflow)?;
+        // Update stake amount and total staked
+        remove_from_stake(&mut ctx.accounts.project, &mut ctx.accounts.stake, amount, current_time)?;
         
         // ✅ Only create seeds when needed
-        let seeds = &[
-            b"project",
-            ctx.accounts.project.token_mint.as_ref(),
-            &ctx.accounts.project.pool_id.to_le_bytes(),
-            &[ctx.accounts.project.bump],
-        ];
+        let seeds = project_signer_seeds!(ctx.accounts.project);
         let signer = &[&seeds[..]];
         
         // ✅ Transfer tokens to user (supports SPL, Token-2022, Native SOL)
@@ -677,7 +830,7 @@ pub mod staking_program {
             &ctx.accounts.token_mint_account,
             ctx.accounts.token_program.to_account_info(),
             ctx.accounts.system_program.to_account_info(),
-            amount_after_fee,
+            amount_to_user,
             Some(signer),
         )?;
         
@@ -694,86 +847,84 @@ pub mod staking_program {
                 Some(signer),
             )?;
         }
-        
-        // Collect SOL fee
-        if ctx.accounts.platform.platform_sol_fee > 0 {
-            if let Some(referrer_key) = ctx.accounts.project.referrer {
-                // Get referrer from remaining_accounts if present
-                let referrer_account = if !ctx.remaining_accounts.is_empty() {
-                    Some(&ctx.remaining_accounts[0])
-                } else {
-                    None
-                };
-                
-                if let Some(referrer_account) = referrer_account {
-                    require!(
-                        referrer_account.key() == referrer_key,
-                        ErrorCode::InvalidReferrer
-                    );
-                    
-                    let referrer_amount = ctx.accounts.platform.platform_sol_fee
-                        .checked_mul(ctx.accounts.project.referrer_split_bps)
-                        .ok_or(ErrorCode::MathOverflow)?
-                        .checked_div(10000)
-                        .ok_or(ErrorCode::MathOverflow)?;
-                    
-                    let admin_amount = ctx.accounts.platform.platform_sol_fee
-                        .checked_sub(referrer_amount)
-                        .ok_or(ErrorCode::MathOverflow)?;
-                    
-                    system_program::transfer(
-                        CpiContext::new(
-                            ctx.accounts.system_program.to_account_info(),
-                            system_program::Transfer {
-                                from: ctx.accounts.user.to_account_info(),
-                                to: ctx.accounts.fee_collector.to_account_info(),
-                            },
-                        ),
-                        admin_amount,
+
+        if penalty > 0 {
+            let destination = ctx.accounts.project.penalty_destination;
+            match destination {
+                PENALTY_TO_BURN => burn(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        Burn {
+                            mint: ctx.accounts.token_mint_account.to_account_info(),
+                            from: ctx.accounts.staking_vault.to_account_info(),
+                            authority: ctx.accounts.project.to_account_info(),
+                        },
+                        signer,
+                    ),
+                    penalty,
+                )?,
+                PENALTY_TO_FEE_COLLECTOR => transfer_tokens(
+                    ctx.accounts.staking_vault.to_account_info(),
+                    ctx.accounts.fee_collector_token_account.to_account_info(),
+                    ctx.accounts.project.to_account_info(),
+                    &ctx.accounts.token_mint_account,
+                    ctx.accounts.token_program.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    penalty,
+                    Some(signer),
+                )?,
+                _ => {
+                    // Same-mint pools only (checked when the penalty is configured)
+                    let reward_vault = ctx.accounts.reward_vault
+                        .as_ref()
+                        .ok_or(ErrorCode::PenaltyAccountsRequired)?;
+                    let is_native = is_native_sol(&ctx.accounts.token_mint_account.key());
+                    let balance_before = vault_balance(reward_vault, is_native)?;
+                    transfer_tokens(
+                        ctx.accounts.staking_vault.to_account_info(),
+                        reward_vault.to_account_info(),
+                        ctx.accounts.project.to_account_info(),
+                        &ctx.accounts.token_mint_account,
+                        ctx.accounts.token_program.to_account_info(),
+                        ctx.accounts.system_program.to_account_info(),
+                        penalty,
+                        Some(signer),
                     )?;
-                    
-                    if referrer_amount > 0 {
-                        system_program::transfer(
-                            CpiContext::new(
-                                ctx.accounts.system_program.to_account_info(),
-                                system_program::Transfer {
-                                    from: ctx.accounts.user.to_account_info(),
-                                    to: referrer_account.to_account_info(),
-                                },
-                            ),
-                            referrer_amount,
-                        )?;
-                    }
-                } else {
-                    system_program::transfer(
-                        CpiContext::new(
-                            ctx.accounts.system_program.to_account_info(),
-                            system_program::Transfer {
-                                from: ctx.accounts.user.to_account_info(),
-                                to: ctx.accounts.fee_collector.to_account_info(),
-                            },
-                        ),
-                        ctx.accounts.platform.platform_sol_fee,
+                    let received = vault_balance(reward_vault, is_native)?
+                        .checked_sub(balance_before)
+                        .ok_or(ErrorCode::MathOverflow)?;
+                    credit_penalty(
+                        &mut ctx.accounts.project,
+                        received,
+                        destination == PENALTY_TO_STAKERS,
+                        current_time,
                     )?;
                 }
-            } else {
-                system_program::transfer(
-                    CpiContext::new(
-                        ctx.accounts.system_program.to_account_info(),
-                        system_program::Transfer {
-                            from: ctx.accounts.user.to_account_info(),
-                            to: ctx.accounts.fee_collector.to_account_info(),
-                        },
-                    ),
-                    ctx.accounts.platform.platform_sol_fee,
-                )?;
             }
+
+            emit!(EarlyExitPenaltyCharged {
+                user: ctx.accounts.user.key(),
+                project: ctx.accounts.project.key(),
+                amount,
+                penalty,
+                destination,
+            });
         }
         
+        // Collect SOL fee
+        collect_sol_fee(
+            &ctx.accounts.project,
+            ctx.accounts.platform.platform_sol_fee,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.fee_collector,
+            fee_accounts,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        
         emit!(TokensWithdrawn {
             user: ctx.accounts.user.key(),
             project: ctx.accounts.project.key(),
-            amount: amount_after_fee,
+            amount: amount_to_user,
             remaining: ctx.accounts.stake.amount,
         });
         
@@ -781,34 +932,29 @@ pub mod staking_program {
     }
     pub fn claim<'info>(
         ctx: Context<'_, '_, '_, 'info, Claim<'info>>,
-        token_mint: Pubkey,
-        pool_id: u64
+        _token_mint: Pubkey,
+        _pool_id: u64
     ) -> Result<()> {
-    require!(ctx.accounts.stake.amount > 0, ErrorCode::NoStake);
+    // Rewards left after the principal was returned or withdrawn stay claimable
+    require!(
+        ctx.accounts.stake.amount > 0 || ctx.accounts.stake.rewards_pending > 0,
+        ErrorCode::NoStake
+    );
+    ctx.accounts.validate()?;
     
     let platform_sol_fee = ctx.accounts.platform.platform_sol_fee;
-    let fee_collector = ctx.accounts.platform.fee_collector;
     
     let project_is_initialized = ctx.accounts.project.is_initialized;
     let project_is_paused = ctx.accounts.project.is_paused;
     let project_claim_paused = ctx.accounts.project.claim_paused;
-    let project_token_mint = ctx.accounts.project.token_mint;
-    let project_pool_id = ctx.accounts.project.pool_id;
-    let project_bump = ctx.accounts.project.bump;
     let project_key = ctx.accounts.project.key();
-    let project_referrer = ctx.accounts.project.referrer;
-    let project_referrer_split_bps = ctx.accounts.project.referrer_split_bps;
     
     require!(project_is_initialized, ErrorCode::NotInitialized);
     require!(!project_is_paused, ErrorCode::ProjectPaused);
     require!(!project_claim_paused, ErrorCode::ClaimsPaused);
+    require_not_globally_paused(&ctx.accounts.platform, PAUSE_CLAIM)?;
     
-    // Check user has enough SOL for fee
-    let rent_minimum = 890880u64;
-    require!(
-        ctx.accounts.user.lamports() >= platform_sol_fee.saturating_add(rent_minimum),
-        ErrorCode::InsufficientSolForFee
-    );
+    require_sol_for_fee(&ctx.accounts.user, platform_sol_fee)?;
     
     update_reward(&mut ctx.accounts.project, &mut ctx.accounts.stake)?;
     
@@ -837,7 +983,6 @@ pub mod staking_program {
     
     let stake_mut = &mut ctx.accounts.stake;
     stake_mut.rewards_pending = 0;
-    stake_mut.last_stake_timestamp = Clock::get()?.unix_timestamp;
     stake_mut.total_rewards_claimed = stake_mut.total_rewards_claimed
         .checked_add(rewards)
         .ok_or(ErrorCode::MathOverflow)?;
@@ -846,13 +991,9 @@ pub mod staking_program {
     project_mut.total_rewards_claimed = project_mut.total_rewards_claimed
         .checked_add(rewards)
         .ok_or(ErrorCode::MathOverflow)?;
+    project_mut.total_rewards_pending = project_mut.total_rewards_pending.saturating_sub(rewards);
     
-    let seeds = &[
-        b"project",
-        project_token_mint.as_ref(),
-        &project_pool_id.to_le_bytes(),
-        &[project_bump],
-    ];
+    let seeds = project_signer_seeds!(ctx.accounts.project);
     let signer = &[&seeds[..]];
     
     // ✅ Transfer rewards (supports SPL, Token-2022, Native SOL)
@@ -868,79 +1009,14 @@ pub mod staking_program {
     )?;
     
     // Collect SOL fee (with referral split if applicable)
-    if platform_sol_fee > 0 {
-        if let Some(referrer_key) = project_referrer {
-            // Get referrer from remaining_accounts if present
-            let referrer_account = if !ctx.remaining_accounts.is_empty() {
-                Some(&ctx.remaining_accounts[0])
-            } else {
-                None
-            };
-            
-            if let Some(referrer_account) = referrer_account {
-                require!(
-                    referrer_account.key() == referrer_key,
-                    ErrorCode::InvalidReferrer
-                );
-                
-                let referrer_amount = platform_sol_fee
-                    .checked_mul(project_referrer_split_bps)
-                    .ok_or(ErrorCode::MathOverflow)?
-                    .checked_div(10000)
-                    .ok_or(ErrorCode::MathOverflow)?;
-                
-                let admin_amount = platform_sol_fee
-                    .checked_sub(referrer_amount)
-                    .ok_or(ErrorCode::MathOverflow)?;
-                
-                system_program::transfer(
-                    CpiContext::new(
-                        ctx.accounts.system_program.to_account_info(),
-                        system_program::Transfer {
-                            from: ctx.accounts.user.to_account_info(),
-                            to: ctx.accounts.fee_collector.to_account_info(),
-                        },
-                    ),
-                    admin_amount,
-                )?;
-                
-                if referrer_amount > 0 {
-                    system_program::transfer(
-                        CpiContext::new(
-                            ctx.accounts.system_program.to_account_info(),
-                            system_program::Transfer {
-                                from: ctx.accounts.user.to_account_info(),
-                                to: referrer_account.to_account_info(),
-                            },
-                        ),
-                        referrer_amount,
-                    )?;
-                }
-            } else {
-                system_program::transfer(
-                    CpiContext::new(
-                        ctx.accounts.system_program.to_account_info(),
-                        system_program::Transfer {
-                            from: ctx.accounts.user.to_account_info(),
-                            to: ctx.accounts.fee_collector.to_account_info(),
-                        },
-                    ),
-                    platform_sol_fee,
-                )?;
-            }
-        } else {
-            system_program::transfer(
-                CpiContext::new(
-                    ctx.accounts.system_program.to_account_info(),
-                    system_program::Transfer {
-                        from: ctx.accounts.user.to_account_info(),
-                        to: ctx.accounts.fee_collector.to_account_info(),
-                    },
-                ),
-                platform_sol_fee,
-            )?;
-        }
-    }
+    collect_sol_fee(
+        &ctx.accounts.project,
+        platform_sol_fee,
+        &ctx.accounts.user.to_account_info(),
+        &ctx.accounts.fee_collector,
+        ctx.remaining_accounts,
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
     
     emit!(RewardsClaimed {
         user: ctx.accounts.user.key(),
@@ -953,16 +1029,22 @@ pub mod staking_program {
 
     pub fn claim_reflections(
         ctx: Context<ClaimReflections>,
-        token_mint: Pubkey,
-        pool_id: u64,
+        _token_mint: Pubkey,
+        _pool_id: u64,
     ) -> Result<()> {
-        require!(ctx.accounts.stake.amount > 0, ErrorCode::NoStake);
+        require!(
+            ctx.accounts.stake.amount > 0 || ctx.accounts.stake.reflections_pending > 0,
+            ErrorCode::NoStake
+        );
+        ctx.accounts.validate()?;
         
         let project = &ctx.accounts.project;
         let stake = &mut ctx.accounts.stake;
 
         require!(project.is_initialized, ErrorCode::NotInitialized);
         require!(!project.is_paused, ErrorCode::ProjectPaused);
+        require!(!project.claim_paused, ErrorCode::ClaimsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_REFLECTIONS)?;
         require!(project.enable_reflections, ErrorCode::ReflectionsNotEnabled);
         require!(
             project.reflection_token.is_some(),
@@ -984,98 +1066,21 @@ pub mod staking_program {
         msg!("   Reflection token: {}", reflection_token_mint);
         msg!("   Type: {}", if is_native_sol { "Native SOL" } else { "SPL Token" });
 
-        // Get decimals for transfer_checked (not needed for Native SOL)
-        let decimals = if !is_native_sol {
-            ctx.accounts.reflection_token_mint.decimals
-        } else {
-            9 // Native SOL has 9 decimals
-        };
-
-        // ✅ Apply 99.7% buffer for Native SOL to prevent rent issues
-        let amount_to_transfer = if is_native_sol {
-            amount.saturating_mul(997).saturating_div(1000) // 99.7%
-        } else {
-            amount
-        };
-
-        msg!("   Transfer amount (after buffer): {}", amount_to_transfer);
-
-        // Get PDA seeds for signing
         let project_key = project.key();
-        let project_bump = project.bump;
-        let project_token_mint = project.token_mint;
-        let project_pool_id = project.pool_id;
-        
-        let seeds = &[
-            b"project",
-            project_token_mint.as_ref(),
-            &project_pool_id.to_le_bytes(),
-            &[project_bump],
-        ];
-        let signer_seeds = &[&seeds[..]];
-
-        // ✅ Transfer based on reflection type
-        if is_native_sol {
-            // ✅ Native SOL - manually transfer lamports from Project PDA
-            msg!("💰 Transferring Native SOL lamports from Project PDA");
-            
-            // Manual lamport transfer (works for accounts with data)
-            **ctx.accounts.reflection_vault.try_borrow_mut_lamports()? -= amount_to_transfer;
-            **ctx.accounts.user_reflection_account.try_borrow_mut_lamports()? += amount_to_transfer;
-            
-            msg!("✅ Native SOL transferred successfully");
-            
-        } else {
-            // ✅ SPL/Token-2022 - transfer from Project PDA's standard ATA
-            msg!("🪙 Transferring SPL tokens from Project PDA's ATA");
-            
-            transfer_checked(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    TransferChecked {
-                        from: ctx.accounts.reflection_vault.to_account_info(),
-                        to: ctx.accounts.user_reflection_account.to_account_info(),
-                        authority: ctx.accounts.project.to_account_info(),
-                        mint: ctx.accounts.reflection_token_mint.to_account_info(),
-                    },
-                    signer_seeds,
-                ),
-                amount_to_transfer,
-                decimals,
-            )?;
-            
-            msg!("✅ SPL tokens transferred successfully");
-        }
-
-        // Reset pending reflections
-        stake.reflections_pending = 0;
-        stake.total_reflections_claimed = stake.total_reflections_claimed
-            .checked_add(amount_to_transfer)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        // Update last balance to current vault balance
-        let current_vault_balance = if is_native_sol {
-            let total_lamports = ctx.accounts.reflection_vault.lamports();
-            let rent = Rent::get()?;
-            let rent_exempt_minimum = rent.minimum_balance(ctx.accounts.reflection_vault.data_len());
-            let fixed_buffer = rent_exempt_minimum.saturating_add(3_000_000);
-            total_lamports.saturating_sub(fixed_buffer)
-        } else {
-            let vault_data = ctx.accounts.reflection_vault.try_borrow_data()?;
-            if vault_data.len() >= 72 {
-                u64::from_le_bytes(vault_data[64..72].try_into().unwrap())
-            } else {
-                0
-            }
-        };
-        
-        // Update project's last reflection balance
-        let project_mut = &mut ctx.accounts.project;
-        project_mut.last_reflection_balance = current_vault_balance;
+        let project_info = ctx.accounts.project.to_account_info();
+        let amount_to_transfer = pay_reflections(
+            &mut ctx.accounts.project,
+            &project_info,
+            &mut ctx.accounts.stake,
+            &ctx.accounts.reflection_vault,
+            &ctx.accounts.user_reflection_account,
+            &ctx.accounts.reflection_token_mint,
+            &ctx.accounts.token_program.to_account_info(),
+        )?;
 
         msg!("✅ Reflections claimed successfully");
         msg!("   Amount transferred: {}", amount_to_transfer);
-        msg!("   New vault balance: {}", current_vault_balance);
+        msg!("   New vault balance: {}", ctx.accounts.project.last_reflection_balance);
         
         emit!(ReflectionsClaimed {
             user: ctx.accounts.user.key(),
@@ -1086,1136 +1091,5714 @@ pub mod staking_program {
         Ok(())
     }
 
-    pub fn refresh_reflections(
-        ctx: Context<RefreshReflections>,
-        token_mint: Pubkey,
-        pool_id: u64,
+    pub fn exit<'info>(
+        ctx: Context<'_, '_, '_, 'info, Exit<'info>>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
     ) -> Result<()> {
-        require!(ctx.accounts.stake.amount > 0, ErrorCode::NoStake);
+        ctx.accounts.validate()?;
+
         require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
-        require!(ctx.accounts.project.enable_reflections, ErrorCode::ReflectionsNotEnabled);
-        require!(
-            ctx.accounts.project.reflection_token.is_some(),
-            ErrorCode::ReflectionTokenRequired
-        );
+        require!(!ctx.accounts.project.is_paused, ErrorCode::ProjectPaused);
+        require!(!ctx.accounts.project.withdraw_paused, ErrorCode::WithdrawalsPaused);
+        require!(!ctx.accounts.project.claim_paused, ErrorCode::ClaimsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_WITHDRAW | PAUSE_CLAIM)?;
+        require!(ctx.accounts.project.unbonding_seconds == 0, ErrorCode::UnbondingRequired);
+        require!(ctx.accounts.stake.unbonding_amount == 0, ErrorCode::UnbondingPending);
 
-        let project = &ctx.accounts.project;
-        let reflection_token_mint = project.reflection_token.unwrap();
-        
-        // Check if it's Native SOL
-        let native_sol = Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
-        let is_native_sol = reflection_token_mint == native_sol;
+        require_sol_for_fee(&ctx.accounts.user, ctx.accounts.platform.platform_sol_fee)?;
 
-        msg!("🔄 Refreshing reflections:");
-        msg!("   User: {}", ctx.accounts.user.key());
-        msg!("   Reflection type: {}", if is_native_sol { "Native SOL" } else { "SPL Token" });
-        msg!("   Reflection token: {}", reflection_token_mint);
+        update_reward(&mut ctx.accounts.project, &mut ctx.accounts.stake)?;
+        update_reflection(&mut ctx.accounts.project, &mut ctx.accounts.stake, ctx.accounts.reflection_vault.as_ref())?;
 
-        // Update reflection calculation - MUST update user paid marker to prevent double-counting
-        update_reflection_internal(
-            &mut ctx.accounts.project,
-            &mut ctx.accounts.stake,
-            Some(&ctx.accounts.reflection_vault.to_account_info()),
-            true,
-        )?;
+        let amount = ctx.accounts.stake.amount;
+        let current_time = Clock::get()?.unix_timestamp;
+        if amount > 0 {
+            require_unlocked(&ctx.accounts.project, &ctx.accounts.stake, amount, current_time)?;
+            require!(
+                ctx.accounts.staking_vault.amount >= amount,
+                ErrorCode::InsufficientVaultBalance
+            );
+        }
 
-        msg!("✅ Reflections refreshed");
-        msg!("   Pending reflections: {}", ctx.accounts.stake.reflections_pending);
-        msg!("   Last balance: {}", ctx.accounts.project.last_reflection_balance);
+        let token_fee = amount
+            .checked_mul(ctx.accounts.platform.platform_token_fee_bps)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
 
-        Ok(())
-    }
+        let amount_after_fee = amount
+            .checked_sub(token_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
 
-    pub fn deposit_rewards(
-        ctx: Context<DepositRewards>,
-        token_mint: Pubkey,
-        pool_id: u64,
-        amount: u64
-    ) -> Result<()> {
-    require!(amount > 0, ErrorCode::InvalidAmount);
-    require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
-    require!(!ctx.accounts.project.is_paused, ErrorCode::ProjectPaused);
-    
-    let project_key = ctx.accounts.project.key();
-    
-    // ✅ Detect if Native SOL or SPL token
-    let is_native = is_native_sol(&ctx.accounts.token_mint_account.key());
+        // Stream rewards must be claimed with claim_stream before the stake closes
+        let project_key = ctx.accounts.project.key();
+        let (fee_accounts, stream_accounts) = split_remaining_accounts(&ctx.accounts.project, ctx.remaining_accounts)?;
+        settle_reward_streams(&project_key, &ctx.accounts.project, &mut ctx.accounts.stake, stream_accounts)?;
+        require!(
+            ctx.accounts.stake.stream_checkpoints.iter().all(|c| c.rewards_pending == 0),
+            ErrorCode::StreamRewardsPending
+        );
 
-    // ✅ Get vault balance BEFORE transfer (handle both Native SOL and SPL)
-    let reward_vault_balance_before = if is_native {
-        ctx.accounts.reward_vault.lamports()
-    } else {
-        // For SPL tokens, read amount from TokenAccount data
-        let vault_data = ctx.accounts.reward_vault.try_borrow_data()?;
-        u64::from_le_bytes(vault_data[64..72].try_into().unwrap())
-    };
+        remove_from_stake(&mut ctx.accounts.project, &mut ctx.accounts.stake, amount, current_time)?;
 
-    // ✅ Transfer rewards to vault (supports SPL, Token-2022, Native SOL)
-    // Tax will be applied automatically by token program if token has transfer tax
-    transfer_tokens(
-        ctx.accounts.admin_token_account.to_account_info(),
-        ctx.accounts.reward_vault.to_account_info(),
-        ctx.accounts.admin.to_account_info(),
-        &ctx.accounts.token_mint_account,
-        ctx.accounts.token_program.to_account_info(),
-        ctx.accounts.system_program.to_account_info(),
-        amount,
-        None,
-    )?;
+        let seeds = project_signer_seeds!(ctx.accounts.project);
+        let signer = &[&seeds[..]];
 
-    // ✅ Get vault balance AFTER transfer (handle both Native SOL and SPL)
-    let reward_vault_balance_after = if is_native {
-        ctx.accounts.reward_vault.lamports()
-    } else {
-        // For SPL tokens, read amount from TokenAccount data
-        let vault_data = ctx.accounts.reward_vault.try_borrow_data()?;
-        u64::from_le_bytes(vault_data[64..72].try_into().unwrap())
-    };
+        // Principal (minus token fee) to the withdrawal wallet
+        if amount_after_fee > 0 {
+            transfer_tokens(
+                ctx.accounts.staking_vault.to_account_info(),
+                ctx.accounts.withdrawal_token_account.to_account_info(),
+                ctx.accounts.project.to_account_info(),
+                &ctx.accounts.token_mint_account,
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                amount_after_fee,
+                Some(signer),
+            )?;
+        }
 
-    let actual_received = reward_vault_balance_after
-        .checked_sub(reward_vault_balance_before)
-        .ok_or(ErrorCode::MathOverflow)?;
-    
-    msg!("💰 Admin deposited {} rewards, vault received {} (after any transfer tax)", amount, actual_received);
-    
-    let project_mut = &mut ctx.accounts.project;
-    project_mut.total_rewards_deposited = project_mut.total_rewards_deposited
-        .checked_add(actual_received)  // ✅ Use actual_received!
-        .ok_or(ErrorCode::MathOverflow)?;
-    
-    // For fixed APY pools (rate_mode = 0), rate was already set in initialize_pool
-    match project_mut.rate_mode {
-        0 => {
-            // Fixed APY - rate already calculated in initialize_pool, don't recalculate
-            msg!("Fixed APY pool - rate: {}", project_mut.reward_rate_per_second);
+        if token_fee > 0 {
+            transfer_tokens(
+                ctx.accounts.staking_vault.to_account_info(),
+                ctx.accounts.fee_collector_token_account.to_account_info(),
+                ctx.accounts.project.to_account_info(),
+                &ctx.accounts.token_mint_account,
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                token_fee,
+                Some(signer),
+            )?;
         }
-        
-        1 => {
-            // Dynamic pool - recalculate based on total available rewards
-            let total_available = project_mut.total_rewards_deposited
-                .checked_sub(project_mut.total_rewards_claimed)
+
+        // Pending rewards to the withdrawal wallet
+        let rewards = ctx.accounts.stake.rewards_pending;
+        if rewards > 0 {
+            let is_native = is_native_sol(&ctx.accounts.reward_mint_account.key());
+            require!(
+                vault_balance(&ctx.accounts.reward_vault, is_native)? >= rewards,
+                ErrorCode::InsufficientRewardVault
+            );
+
+            transfer_tokens(
+                ctx.accounts.reward_vault.to_account_info(),
+                ctx.accounts.reward_token_account.to_account_info(),
+                ctx.accounts.project.to_account_info(),
+                &ctx.accounts.reward_mint_account,
+                ctx.accounts.reward_token_program.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                rewards,
+                Some(signer),
+            )?;
+
+            let stake = &mut ctx.accounts.stake;
+            stake.rewards_pending = 0;
+            stake.total_rewards_claimed = stake.total_rewards_claimed
+                .checked_add(rewards)
                 .ok_or(ErrorCode::MathOverflow)?;
-            
-            let current_time = Clock::get()?.unix_timestamp;
-            let time_remaining = project_mut.pool_end_time
-                .checked_sub(current_time)
-                .unwrap_or(0);
-            
-            if time_remaining > 0 {
-                project_mut.reward_rate_per_second = total_available
-                    .checked_div(time_remaining as u64)
-                    .unwrap_or(0);
-            }
-            
-            msg!("Dynamic pool - new rate: {}", project_mut.reward_rate_per_second);
+
+            let project = &mut ctx.accounts.project;
+            project.total_rewards_claimed = project.total_rewards_claimed
+                .checked_add(rewards)
+                .ok_or(ErrorCode::MathOverflow)?;
+            project.total_rewards_pending = project.total_rewards_pending.saturating_sub(rewards);
         }
-        
-        _ => return Err(ErrorCode::InvalidRateMode.into()),
-    }
-    
-    emit!(RewardsDeposited {
-        project: project_key,
-        amount: actual_received,  // ✅ Emit actual received amount
-        total_rewards: project_mut.total_rewards_deposited,
-        reward_rate: project_mut.reward_rate_per_second,
-    });
-    
-    Ok(())
-}
 
-    pub fn update_referrer(
-        ctx: Context<UpdateReferrer>,
-        token_mint: Pubkey,
-        pool_id: u64,
-        referrer: Option<Pubkey>,
-        split_bps: u64,
-    ) -> Result<()> {
-        require!(split_bps <= 10000, ErrorCode::InvalidSplitPercentage);
-        
-        let project = &mut ctx.accounts.project;
-        project.referrer = referrer;
-        project.referrer_split_bps = split_bps;
-        
-        emit!(ReferrerUpdated {
-            project: project.key(),
-            referrer,
-            split_bps,
+        // Pending reflections, which need the optional reflection accounts
+        let reflections = if ctx.accounts.stake.reflections_pending > 0 {
+            require_not_globally_paused(&ctx.accounts.platform, PAUSE_REFLECTIONS)?;
+
+            let (Some(reflection_vault), Some(user_reflection_account), Some(reflection_token_mint), Some(reflection_token_program)) = (
+                ctx.accounts.reflection_vault.as_ref(),
+                ctx.accounts.user_reflection_account.as_ref(),
+                ctx.accounts.reflection_token_mint.as_ref(),
+                ctx.accounts.reflection_token_program.as_ref(),
+            ) else {
+                return Err(ErrorCode::ReflectionAccountsRequired.into());
+            };
+
+            require!(
+                Some(reflection_token_mint.key()) == ctx.accounts.project.reflection_token,
+                ErrorCode::WrongTokenType
+            );
+            validate_withdrawal_account(
+                user_reflection_account,
+                &reflection_token_program.key(),
+                &reflection_token_mint.key(),
+                &ctx.accounts.stake,
+            )?;
+
+            let project_info = ctx.accounts.project.to_account_info();
+            pay_reflections(
+                &mut ctx.accounts.project,
+                &project_info,
+                &mut ctx.accounts.stake,
+                reflection_vault,
+                user_reflection_account,
+                reflection_token_mint,
+                &reflection_token_program.to_account_info(),
+            )?
+        } else {
+            0
+        };
+
+        // Collect SOL fee once for the whole exit
+        collect_sol_fee(
+            &ctx.accounts.project,
+            ctx.accounts.platform.platform_sol_fee,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.fee_collector,
+            fee_accounts,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(StakeExited {
+            user: ctx.accounts.user.key(),
+            project: ctx.accounts.project.key(),
+            amount: amount_after_fee,
+            rewards,
+            reflections,
         });
-        
+
         Ok(())
     }
 
-    pub fn set_fees(
-        ctx: Context<SetFees>,
-        platform_token_fee_bps: u64,
-        platform_sol_fee: u64,
+    /// Closes an empty stake. Works after close_project too, so rent is never stranded.
+    pub fn close_stake(
+        ctx: Context<CloseStake>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
     ) -> Result<()> {
-        let platform = &mut ctx.accounts.platform;
-        platform.platform_token_fee_bps = platform_token_fee_bps;
-        platform.platform_sol_fee = platform_sol_fee;
-        
-        emit!(FeesUpdated {
-            platform_token_fee_bps,
-            platform_sol_fee,
+        let stake = &ctx.accounts.stake;
+
+        require!(
+            stake.amount == 0
+                && stake.unbonding_amount == 0
+                && stake.rewards_pending == 0
+                && stake.reflections_pending == 0,
+            ErrorCode::StakeNotEmpty
+        );
+        require!(
+            stake.stream_checkpoints.iter().all(|c| c.rewards_pending == 0),
+            ErrorCode::StreamRewardsPending
+        );
+
+        emit!(StakeClosed {
+            user: ctx.accounts.user.key(),
+            project: ctx.accounts.project.key(),
         });
-        
-        Ok(())
-    }
 
-    pub fn pause_project(
-        ctx: Context<PauseControl>,
-        token_mint: Pubkey,
-        pool_id: u64
-    ) -> Result<()> {
-        ctx.accounts.project.is_paused = true;
-        emit!(ProjectPaused { project: ctx.accounts.project.key() });
         Ok(())
     }
 
-    pub fn unpause_project(
-        ctx: Context<PauseControl>,
-        token_mint: Pubkey,
-        pool_id: u64
+    /// Moves `amount` out of the stake into the unbonding queue. It stops earning at
+    /// once and can be taken with complete_unstake after unbonding_seconds. A new
+    /// request restarts the cooldown for everything pending. Rewards earned up to the
+    /// request stay claimable, even once the whole stake is unbonding.
+    /// remaining_accounts: the project's reward streams in index order
+    pub fn request_unstake<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestUnstake<'info>>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        amount: u64,
     ) -> Result<()> {
-        ctx.accounts.project.is_paused = false;
-        emit!(ProjectUnpaused { project: ctx.accounts.project.key() });
-        Ok(())
-    }
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(ctx.accounts.stake.amount >= amount, ErrorCode::InsufficientBalance);
+        ctx.accounts.validate()?;
 
-    pub fn emergency_unlock(
-        ctx: Context<EmergencyUnlockAccounts>,
-        token_mint: Pubkey,
-        pool_id: u64
-    ) -> Result<()> {
-        ctx.accounts.project.lockup_seconds = 0;
-        emit!(EmergencyUnlockEvent { 
-            project: ctx.accounts.project.key(),
-            admin: ctx.accounts.admin.key(),
+        require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
+        require!(!ctx.accounts.project.is_paused, ErrorCode::ProjectPaused);
+        require!(!ctx.accounts.project.withdraw_paused, ErrorCode::WithdrawalsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_WITHDRAW)?;
+
+        update_reward(&mut ctx.accounts.project, &mut ctx.accounts.stake)?;
+        update_reflection(&mut ctx.accounts.project, &mut ctx.accounts.stake, ctx.accounts.reflection_vault.as_ref())?;
+
+        let project_key = ctx.accounts.project.key();
+        settle_reward_streams(&project_key, &ctx.accounts.project, &mut ctx.accounts.stake, ctx.remaining_accounts)?;
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let project = &mut ctx.accounts.project;
+        let stake = &mut ctx.accounts.stake;
+        let ready_time = queue_unbonding(project, stake, amount, current_time)?;
+
+        emit!(UnstakeRequested {
+            user: stake.user,
+            project: project_key,
+            amount,
+            unbonding_amount: stake.unbonding_amount,
+            ready_time,
         });
+
         Ok(())
     }
 
-    pub fn claim_unclaimed_tokens(
-        ctx: Context<ClaimUnclaimedTokens>,
-        token_mint: Pubkey,
-        pool_id: u64,
-        amount: u64
+    /// Pays out the unbonding amount once its cooldown has passed.
+    /// remaining_accounts: optional referrer wallet for the SOL fee split
+    pub fn complete_unstake<'info>(
+        ctx: Context<'_, '_, '_, 'info, CompleteUnstake<'info>>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
     ) -> Result<()> {
-        let project = &ctx.accounts.project;
+        ctx.accounts.validate()?;
 
-        // ✅ For AccountInfo, we need to manually check the balance
-        let is_native = is_native_sol(&ctx.accounts.token_mint_account.key());
+        require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
+        require!(!ctx.accounts.project.is_paused, ErrorCode::ProjectPaused);
+        require!(!ctx.accounts.project.withdraw_paused, ErrorCode::WithdrawalsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_WITHDRAW)?;
 
-        if !is_native {
-            // For SPL tokens, read the amount field from TokenAccount data
-            let vault_data = ctx.accounts.vault.try_borrow_data()?;
-            if vault_data.len() >= 72 {
-                let vault_amount = u64::from_le_bytes(vault_data[64..72].try_into().unwrap());
-                require!(
-                    vault_amount >= amount,
-                    ErrorCode::InsufficientVaultBalance
-                );
-            }
-        } else {
-            // For Native SOL, check lamports
-            require!(
-                ctx.accounts.vault.lamports() >= amount,
-                ErrorCode::InsufficientVaultBalance
-            );
-        }
-        
-        let seeds = &[
-            b"project",
-            project.token_mint.as_ref(),
-            &project.pool_id.to_le_bytes(),
-            &[project.bump],
-        ];
-        let signer = &[&seeds[..]];
-        
-        // ✅ Transfer tokens to admin (supports SPL, Token-2022, Native SOL)
-        transfer_tokens(
-            ctx.accounts.vault.to_account_info(),
-            ctx.accounts.admin_token_account.to_account_info(),
+        let current_time = Clock::get()?.unix_timestamp;
+        let amount = take_unbonded(&mut ctx.accounts.project, &mut ctx.accounts.stake, current_time)?;
+
+        let platform_sol_fee = ctx.accounts.platform.platform_sol_fee;
+        require_sol_for_fee(&ctx.accounts.user, platform_sol_fee)?;
+        require!(
+            ctx.accounts.staking_vault.amount >= amount,
+            ErrorCode::InsufficientVaultBalance
+        );
+
+        let token_fee = amount
+            .checked_mul(ctx.accounts.platform.platform_token_fee_bps)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let amount_after_fee = amount
+            .checked_sub(token_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let seeds = project_signer_seeds!(ctx.accounts.project);
+        let signer = &[&seeds[..]];
+
+        transfer_tokens(
+            ctx.accounts.staking_vault.to_account_info(),
+            ctx.accounts.withdrawal_token_account.to_account_info(),
             ctx.accounts.project.to_account_info(),
             &ctx.accounts.token_mint_account,
             ctx.accounts.token_program.to_account_info(),
             ctx.accounts.system_program.to_account_info(),
-            amount,
+            amount_after_fee,
             Some(signer),
         )?;
-        
+
+        if token_fee > 0 {
+            transfer_tokens(
+                ctx.accounts.staking_vault.to_account_info(),
+                ctx.accounts.fee_collector_token_account.to_account_info(),
+                ctx.accounts.project.to_account_info(),
+                &ctx.accounts.token_mint_account,
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                token_fee,
+                Some(signer),
+            )?;
+        }
+
+        collect_sol_fee(
+            &ctx.accounts.project,
+            platform_sol_fee,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.fee_collector,
+            ctx.remaining_accounts,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(UnstakeCompleted {
+            user: ctx.accounts.user.key(),
+            project: ctx.accounts.project.key(),
+            amount: amount_after_fee,
+            token_fee,
+        });
+
         Ok(())
     }
 
-    pub fn update_fee_collector(
-        ctx: Context<UpdateFeeCollector>,
-        new_fee_collector: Pubkey,
+    /// Puts the whole unbonding amount back into the stake. It earns again at once
+    /// and, having already served its lock, is not locked again.
+    /// remaining_accounts: the project's reward streams in index order
+    pub fn cancel_unstake<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestUnstake<'info>>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
     ) -> Result<()> {
-        let platform = &mut ctx.accounts.platform;
-        let old_fee_collector = platform.fee_collector;
-        platform.fee_collector = new_fee_collector;
-        
-        emit!(FeeCollectorUpdated {
-            old_fee_collector,
-            new_fee_collector,
+        ctx.accounts.validate()?;
+
+        require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
+        require!(!ctx.accounts.project.is_paused, ErrorCode::ProjectPaused);
+        require!(!ctx.accounts.project.deposit_paused, ErrorCode::DepositsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_DEPOSIT)?;
+
+        require!(ctx.accounts.stake.unbonding_amount > 0, ErrorCode::NothingUnbonding);
+
+        update_reward(&mut ctx.accounts.project, &mut ctx.accounts.stake)?;
+        update_reflection(&mut ctx.accounts.project, &mut ctx.accounts.stake, ctx.accounts.reflection_vault.as_ref())?;
+
+        let project_key = ctx.accounts.project.key();
+        settle_reward_streams(&project_key, &ctx.accounts.project, &mut ctx.accounts.stake, ctx.remaining_accounts)?;
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let project = &mut ctx.accounts.project;
+        let stake = &mut ctx.accounts.stake;
+        let amount = restake_unbonding(project, stake, current_time)?;
+
+        emit!(UnstakeCancelled {
+            user: stake.user,
+            project: project_key,
+            amount,
+            new_total: stake.amount,
         });
-        
+
         Ok(())
     }
-}
 
-fn update_reward(project: &mut Account<Project>, stake: &mut Account<Stake>) -> Result<()> {
-    let current_time = Clock::get()?.unix_timestamp;
-    
-    // Update project's reward tracking (kept for compatibility)
-    if project.total_staked > 0 && current_time > project.last_update_time {
-        let time_delta_i64 = current_time
-            .checked_sub(project.last_update_time)
-            .ok_or(ErrorCode::MathOverflow)?;
-        
-        require!(time_delta_i64 >= 0, ErrorCode::InvalidTimestamp);
-        let time_delta = time_delta_i64 as u64;
-        
-        let effective_time = if current_time > project.pool_end_time {
-            let time_until_end = project.pool_end_time
-                .checked_sub(project.last_update_time)
-                .unwrap_or(0)
-                .max(0);
-            
-            require!(time_until_end >= 0, ErrorCode::InvalidTimestamp);
-            time_until_end as u64
-        } else {
-            time_delta
-        };
-        
-        if effective_time > 0 {
-            let intermediate = (project.reward_rate_per_second as u128)
-                .checked_mul(effective_time as u128)
-                .ok_or(ErrorCode::MathOverflow)?;
-            
-            let result_u128 = intermediate
-                .checked_div(project.total_staked as u128)
-                .ok_or(ErrorCode::DivisionByZero)?;
-            
-            require!(result_u128 <= u64::MAX as u128, ErrorCode::MathOverflow);
-            let rewards_per_token = result_u128 as u64;
-            
-            project.reward_per_token_stored = project.reward_per_token_stored
-                .checked_add(rewards_per_token)
-                .ok_or(ErrorCode::MathOverflow)?;
-        }
-    }
-    
-    project.last_update_time = current_time;
-    
-    // Calculate rewards for this specific stake
-    if stake.amount > 0 && project.total_staked > 0 {
-        let stake_last_update = stake.last_stake_timestamp;
-        let time_since_stake_update = current_time
-            .checked_sub(stake_last_update)
-            .unwrap_or(0)
-            .max(0);
-        
-        require!(time_since_stake_update >= 0, ErrorCode::InvalidTimestamp);
-        
-        // Adjust for pool end time
-        let effective_time = if current_time > project.pool_end_time {
-            if stake_last_update >= project.pool_end_time {
-                0
-            } else {
-                (project.pool_end_time - stake_last_update).max(0) as u64
-            }
-        } else {
-            time_since_stake_update as u64
-        };
-        
-        if effective_time > 0 {
-            let intermediate = (project.reward_rate_per_second as u128)
-                .checked_mul(effective_time as u128)
-                .ok_or(ErrorCode::MathOverflow)?
-                .checked_mul(stake.amount as u128)
-                .ok_or(ErrorCode::MathOverflow)?;
-            
-            // ✅ Check rate_mode to use correct formula
-            let new_rewards_u128 = if project.rate_mode == 0 {
-                // Fixed APY: reward_rate_per_second has 1e9 scaling
-                // Formula: (amount × rate × time) / 1e9
-                intermediate
-                    .checked_div(1_000_000_000u128)
-                    .ok_or(ErrorCode::DivisionByZero)?
+    pub fn compound<'info>(
+        ctx: Context<'_, '_, '_, 'info, Compound<'info>>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<()> {
+        require!(ctx.accounts.stake.amount > 0, ErrorCode::NoStake);
+        require!(ctx.accounts.project.reward_mint.is_none(), ErrorCode::CompoundRequiresSameMint);
+        ctx.accounts.validate()?;
+
+        require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
+        require!(!ctx.accounts.project.is_paused, ErrorCode::ProjectPaused);
+        require!(!ctx.accounts.project.deposit_paused, ErrorCode::DepositsPaused);
+        require!(!ctx.accounts.project.claim_paused, ErrorCode::ClaimsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_DEPOSIT | PAUSE_CLAIM)?;
+
+        let platform_sol_fee = ctx.accounts.platform.platform_sol_fee;
+        require_sol_for_fee(&ctx.accounts.user, platform_sol_fee)?;
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time < ctx.accounts.project.pool_end_time, ErrorCode::PoolEnded);
+
+        update_reward(&mut ctx.accounts.project, &mut ctx.accounts.stake)?;
+
+        if ctx.accounts.project.enable_reflections {
+            let reflection_vault_account = if let Some(ref vault) = ctx.accounts.reflection_vault {
+                Some(vault.to_account_info())
             } else {
-                // Dynamic pool: reward_rate_per_second is lamports/sec for entire pool
-                // Formula: (amount × rate × time) / totalStaked
-                intermediate
-                    .checked_div(project.total_staked as u128)
-                    .ok_or(ErrorCode::DivisionByZero)?
+                // Native SOL reflections - use project PDA
+                Some(ctx.accounts.project.to_account_info())
             };
-            
-            require!(new_rewards_u128 <= u64::MAX as u128, ErrorCode::MathOverflow);
-            let new_rewards = new_rewards_u128 as u64;
-            
-            stake.rewards_pending = stake.rewards_pending
-                .checked_add(new_rewards)
-                .ok_or(ErrorCode::MathOverflow)?;
+            update_reflection(&mut ctx.accounts.project, &mut ctx.accounts.stake, reflection_vault_account.as_ref())?;
         }
-    }
-    
-    Ok(())
-}
 
-fn update_reflection_internal(
-    project: &mut Account<Project>,
-    stake: &mut Account<Stake>,
-    reflection_vault: Option<&AccountInfo>,
-    update_user_paid_marker: bool,
-) -> Result<()> {
-    if !project.enable_reflections {
-        return Ok(());
-    }
-    
-    if project.reflection_token.is_none() {
-        return Ok(());
-    }
-    
-    let vault = reflection_vault.ok_or(ErrorCode::ReflectionVaultRequired)?;
-    let current_time = Clock::get()?.unix_timestamp;
-    
-    // Determine reflection type
-    let reflection_token_mint = project.reflection_token.unwrap();
-    let native_sol = Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
-    let is_native_sol = reflection_token_mint == native_sol;
-    
-    // ✅ Calculate distributable balance based on reflection type
-    let current_balance = if is_native_sol {
-        // ✅ Native SOL - read from Project PDA lamports with 0.003 SOL buffer
-        let total_lamports = vault.lamports();
-        let rent = Rent::get()?;
-        let rent_exempt_minimum = rent.minimum_balance(vault.data_len());
-        let fixed_buffer = rent_exempt_minimum.saturating_add(3_000_000);  // ✅ 0.003 SOL buffer
-        
-        msg!("🔍 Native SOL reflection calculation:");
-        msg!("   Total lamports: {}", total_lamports);
-        msg!("   Rent exempt minimum: {}", rent_exempt_minimum);
-        msg!("   Fixed buffer (rent + 0.003 SOL): {}", fixed_buffer);
-        msg!("   Distributable balance: {}", total_lamports.saturating_sub(fixed_buffer));
-        
-        total_lamports.saturating_sub(fixed_buffer)
-    } else {
-        // ✅ SPL/Token-2022 - read from Project PDA's standard ATA
-        // (Works for both self-reflection and external reflection)
-        let vault_data = vault.try_borrow_data()?;
-        if vault_data.len() < 72 {
-            return Err(ErrorCode::InvalidReflectionVault.into());
-        }
-        u64::from_le_bytes(vault_data[64..72].try_into().unwrap())
-    };
-    
-    let calc_current_balance = current_balance;
-    let calc_last_balance = project.last_reflection_balance;
-    let calc_new_tokens = current_balance.saturating_sub(project.last_reflection_balance);
-    let calc_total_staked = project.total_staked;
+        let rewards = ctx.accounts.stake.rewards_pending;
+        require!(rewards > 0, ErrorCode::NoRewards);
 
-    // If balance decreased (e.g., admin withdrew reflections), reset baseline
-    if calc_current_balance < calc_last_balance {
-        project.last_reflection_balance = calc_current_balance;
-        project.last_reflection_update_time = current_time;
-        return Ok(());
-    }
-    
-    // Calculate and distribute new reflections
-    if calc_new_tokens > 0 && calc_total_staked > 0 {
-        let per_token_u128 = (calc_new_tokens as u128)
-            .checked_mul(1_000_000_000u128)
-            .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(calc_total_staked as u128)
-            .ok_or(ErrorCode::DivisionByZero)?;
-        
-        require!(per_token_u128 <= u64::MAX as u128, ErrorCode::MathOverflow);
-        let per_token_rate = per_token_u128 as u64;
-        
-        project.reflection_per_token_stored = project.reflection_per_token_stored
-            .checked_add(per_token_rate)
+        let is_native = is_native_sol(&ctx.accounts.token_mint_account.key());
+        require!(
+            vault_balance(&ctx.accounts.reward_vault, is_native)? >= rewards,
+            ErrorCode::InsufficientRewardVault
+        );
+
+        let seeds = project_signer_seeds!(ctx.accounts.project);
+        let signer = &[&seeds[..]];
+
+        let project_key = ctx.accounts.project.key();
+        let (fee_accounts, stream_accounts) = split_remaining_accounts(&ctx.accounts.project, ctx.remaining_accounts)?;
+        settle_reward_streams(&project_key, &ctx.accounts.project, &mut ctx.accounts.stake, stream_accounts)?;
+
+        // Measure what the staking vault actually receives (transfer-fee mints)
+        let vault_balance_before = vault_balance(&ctx.accounts.staking_vault, is_native)?;
+
+        transfer_tokens(
+            ctx.accounts.reward_vault.to_account_info(),
+            ctx.accounts.staking_vault.to_account_info(),
+            ctx.accounts.project.to_account_info(),
+            &ctx.accounts.token_mint_account,
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            rewards,
+            Some(signer),
+        )?;
+
+        let actual_received = vault_balance(&ctx.accounts.staking_vault, is_native)?
+            .checked_sub(vault_balance_before)
             .ok_or(ErrorCode::MathOverflow)?;
-        
-        project.last_reflection_balance = calc_current_balance;
-        project.last_reflection_update_time = current_time;
-        
-        msg!("✅ Reflection update:");
-        msg!("   New reflections: {}", calc_new_tokens);
-        msg!("   Per token rate: {}", per_token_rate);
-        msg!("   Total per token stored: {}", project.reflection_per_token_stored);
-    }
-    
-    // Update user's pending reflections
-    if stake.amount > 0 {
-        let rate_diff = project.reflection_per_token_stored
-            .saturating_sub(stake.reflection_per_token_paid);
-        
-        if rate_diff > 0 {
-            let earned_u128 = (rate_diff as u128)
-                .checked_mul(stake.amount as u128)
-                .ok_or(ErrorCode::MathOverflow)?
-                .checked_div(1_000_000_000u128)
-                .ok_or(ErrorCode::DivisionByZero)?;
-            
-            require!(earned_u128 <= u64::MAX as u128, ErrorCode::MathOverflow);
-            let earned = earned_u128 as u64;
-            
-            let net_earned = earned.saturating_sub(stake.reflection_debt);
-            
-            stake.reflections_pending = stake.reflections_pending
-                .checked_add(net_earned)
-                .ok_or(ErrorCode::MathOverflow)?;
-            
-            stake.reflection_debt = 0;
-            
-            msg!("   User earned: {}", net_earned);
-            msg!("   Total pending: {}", stake.reflections_pending);
-        }
-        
-        if update_user_paid_marker {
-            stake.reflection_per_token_paid = project.reflection_per_token_stored;
+
+        let project = &mut ctx.accounts.project;
+        let stake = &mut ctx.accounts.stake;
+        let old_rate = compound_rewards(project, stake, rewards, actual_received, current_time)?;
+        require_rewards_funded(project)?;
+        if let Some(old_rate) = old_rate {
+            emit!(StakeRateMigrated {
+                user: stake.user,
+                project: project.key(),
+                old_rate,
+                new_rate: stake.reward_rate_snapshot,
+            });
         }
-    }
-    
-    Ok(())
-}
 
-fn update_reflection(
-    project: &mut Account<Project>,
-    stake: &mut Account<Stake>,
-    reflection_vault: Option<&AccountInfo>,
-) -> Result<()> {
-    update_reflection_internal(project, stake, reflection_vault, true)
-}
+        // One SOL fee instead of claim + deposit
+        collect_sol_fee(
+            &ctx.accounts.project,
+            platform_sol_fee,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.fee_collector,
+            fee_accounts,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + Platform::INIT_SPACE,
-        seeds = [b"platform_v2"],
+        emit!(RewardsCompounded {
+            user: ctx.accounts.user.key(),
+            project: ctx.accounts.project.key(),
+            rewards,
+            amount: actual_received,
+            new_total: ctx.accounts.stake.amount,
+        });
+
+        Ok(())
+    }
+
+    /// ADMIN: Add a co-incentive reward stream paying `stream_mint` until `end_time`
+    pub fn add_reward_stream(
+        ctx: Context<AddRewardStream>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        end_time: i64,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            end_time > current_time.max(ctx.accounts.project.pool_start_time),
+            ErrorCode::InvalidStreamEndTime
+        );
+        require!(
+            !is_native_sol(&ctx.accounts.stream_mint.key()),
+            ErrorCode::NativeStreamNotSupported
+        );
+
+        let project = &mut ctx.accounts.project;
+        require!(
+            (project.reward_stream_count as usize) < MAX_REWARD_STREAMS,
+            ErrorCode::RewardStreamLimitReached
+        );
+
+        let stream = &mut ctx.accounts.reward_stream;
+        stream.project = project.key();
+        stream.index = project.reward_stream_count;
+        stream.mint = ctx.accounts.stream_mint.key();
+        stream.vault = ctx.accounts.stream_vault.key();
+        stream.reward_rate_per_second = 0;
+        stream.reward_per_token_stored = 0;
+        stream.last_update_time = current_time;
+        stream.end_time = end_time;
+        stream.total_funded = 0;
+        stream.total_accrued = 0;
+        stream.total_claimed = 0;
+        stream.bump = ctx.bumps.reward_stream;
+        stream.total_pending = 0;
+
+        project.reward_stream_count = project.reward_stream_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(RewardStreamAdded {
+            project: stream.project,
+            stream: stream.key(),
+            index: stream.index,
+            mint: stream.mint,
+            end_time,
+        });
+
+        Ok(())
+    }
+
+    /// Fund a reward stream; its rate is spread over the time left until end_time
+    pub fn fund_reward_stream(
+        ctx: Context<FundRewardStream>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        index: u8,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        validate_token_account(
+            &ctx.accounts.stream_vault.to_account_info(),
+            &ctx.accounts.token_program.key(),
+            &ctx.accounts.stream_mint.key(),
+            &ctx.accounts.project.key(),
+        )?;
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time < ctx.accounts.reward_stream.end_time, ErrorCode::StreamEnded);
+
+        update_stream_pool(&mut ctx.accounts.reward_stream, &ctx.accounts.project)?;
+
+        let vault_balance_before = ctx.accounts.stream_vault.amount;
+
+        transfer_tokens(
+            ctx.accounts.funder_token_account.to_account_info(),
+            ctx.accounts.stream_vault.to_account_info(),
+            ctx.accounts.funder.to_account_info(),
+            &ctx.accounts.stream_mint,
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            amount,
+            None,
+        )?;
+
+        ctx.accounts.stream_vault.reload()?;
+        let actual_received = ctx.accounts.stream_vault.amount
+            .checked_sub(vault_balance_before)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let stream = &mut ctx.accounts.reward_stream;
+        stream.total_funded = stream.total_funded
+            .checked_add(actual_received)
+            .ok_or(ErrorCode::MathOverflow)?;
+        respread_stream_rate(stream, &ctx.accounts.project, current_time)?;
+
+        emit!(RewardStreamFunded {
+            stream: stream.key(),
+            index,
+            amount: actual_received,
+            total_funded: stream.total_funded,
+            reward_rate: stream.reward_rate_per_second,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_stream<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimStream<'info>>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        index: u8,
+    ) -> Result<()> {
+        require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
+        require!(!ctx.accounts.project.is_paused, ErrorCode::ProjectPaused);
+        require!(!ctx.accounts.project.claim_paused, ErrorCode::ClaimsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_CLAIM)?;
+
+        validate_token_account(
+            &ctx.accounts.stream_vault.to_account_info(),
+            &ctx.accounts.token_program.key(),
+            &ctx.accounts.stream_mint.key(),
+            &ctx.accounts.project.key(),
+        )?;
+        validate_withdrawal_account(
+            &ctx.accounts.user_token_account,
+            &ctx.accounts.token_program.key(),
+            &ctx.accounts.stream_mint.key(),
+            &ctx.accounts.stake,
+        )?;
+
+        let platform_sol_fee = ctx.accounts.platform.platform_sol_fee;
+        require_sol_for_fee(&ctx.accounts.user, platform_sol_fee)?;
+
+        update_stream_pool(&mut ctx.accounts.reward_stream, &ctx.accounts.project)?;
+
+        let stake = &mut ctx.accounts.stake;
+        let weight = stake.weight;
+        let checkpoint = &mut stake.stream_checkpoints[index as usize];
+        update_stream_reward(&mut ctx.accounts.reward_stream, checkpoint, weight)?;
+
+        let rewards = checkpoint.rewards_pending;
+        require!(rewards > 0, ErrorCode::NoRewards);
+        require!(
+            ctx.accounts.stream_vault.amount >= rewards,
+            ErrorCode::InsufficientRewardVault
+        );
+        checkpoint.rewards_pending = 0;
+
+        let stream = &mut ctx.accounts.reward_stream;
+        stream.total_claimed = stream.total_claimed
+            .checked_add(rewards)
+            .ok_or(ErrorCode::MathOverflow)?;
+        stream.total_pending = stream.total_pending.saturating_sub(rewards);
+
+        let project = &ctx.accounts.project;
+        let seeds = project_signer_seeds!(project);
+        let signer = &[&seeds[..]];
+
+        transfer_tokens(
+            ctx.accounts.stream_vault.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.project.to_account_info(),
+            &ctx.accounts.stream_mint,
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            rewards,
+            Some(signer),
+        )?;
+
+        collect_sol_fee(
+            &ctx.accounts.project,
+            platform_sol_fee,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.fee_collector,
+            ctx.remaining_accounts,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        emit!(StreamRewardsClaimed {
+            user: ctx.accounts.user.key(),
+            project: ctx.accounts.project.key(),
+            stream: ctx.accounts.reward_stream.key(),
+            amount: rewards,
+        });
+
+        Ok(())
+    }
+
+    /// ADMIN: Sweep stream funds that are not allocated to stakers: funded minus accrued.
+    /// A stream still running is respread over its remaining time at the lower rate.
+    pub fn sweep_reward_stream(
+        ctx: Context<SweepRewardStream>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        index: u8,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        validate_wallet_account(
+            &ctx.accounts.admin_token_account,
+            &ctx.accounts.token_program.key(),
+            &ctx.accounts.stream_mint.key(),
+            &ctx.accounts.admin.key(),
+        )?;
+
+        update_stream_pool(&mut ctx.accounts.reward_stream, &ctx.accounts.project)?;
+
+        let stream = &ctx.accounts.reward_stream;
+        let unallocated = stream.total_funded.saturating_sub(stream.total_accrued);
+        let owed = stream.total_accrued.saturating_sub(stream.total_claimed);
+        let surplus = unallocated.min(ctx.accounts.stream_vault.amount.saturating_sub(owed));
+        require!(amount <= surplus, ErrorCode::AmountExceedsSurplus);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let stream = &mut ctx.accounts.reward_stream;
+        stream.total_funded = stream.total_funded
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if current_time < stream.end_time {
+            respread_stream_rate(stream, &ctx.accounts.project, current_time)?;
+        }
+
+        let project = &ctx.accounts.project;
+        let seeds = project_signer_seeds!(project);
+        let signer = &[&seeds[..]];
+
+        transfer_tokens(
+            ctx.accounts.stream_vault.to_account_info(),
+            ctx.accounts.admin_token_account.to_account_info(),
+            ctx.accounts.project.to_account_info(),
+            &ctx.accounts.stream_mint,
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            amount,
+            Some(signer),
+        )?;
+
+        emit!(RewardStreamSwept {
+            stream: ctx.accounts.reward_stream.key(),
+            index,
+            amount,
+            total_funded: ctx.accounts.reward_stream.total_funded,
+            reward_rate: ctx.accounts.reward_stream.reward_rate_per_second,
+        });
+
+        Ok(())
+    }
+
+    /// Reallocs the platform account created before the current Platform layout.
+    /// New fields start zeroed: no pending admin and no global pause. Anyone may
+    /// pay for it.
+    pub fn migrate_platform(ctx: Context<MigratePlatform>) -> Result<()> {
+        let platform_info = ctx.accounts.platform.to_account_info();
+        let target_len = 8 + Platform::INIT_SPACE;
+
+        require!(platform_info.owner == &crate::ID, ErrorCode::InvalidPlatformAccount);
+        require!(platform_info.data_len() < target_len, ErrorCode::AccountAlreadyMigrated);
+
+        grow_account(
+            &platform_info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            target_len,
+        )?;
+        // Checks the discriminator
+        Platform::try_deserialize(&mut &platform_info.try_borrow_data()?[..])?;
+
+        emit!(PlatformMigrated {
+            platform: platform_info.key(),
+        });
+
+        Ok(())
+    }
+
+    /// ADMIN: Reallocs a project created before the current Project layout, seeds the
+    /// fields older layouts lacked and registers the weight of every legacy stake at
+    /// once, so accrual under the current model starts now for all of them. Legacy
+    /// stakes earn under the old formula up to this cutoff; migrate_stake credits it.
+    pub fn migrate_project(
+        ctx: Context<MigrateProject>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<()> {
+        let project_info = ctx.accounts.project.to_account_info();
+        let target_len = 8 + Project::INIT_SPACE;
+
+        require!(project_info.owner == &crate::ID, ErrorCode::InvalidProject);
+        require!(project_info.data_len() < target_len, ErrorCode::AccountAlreadyMigrated);
+
+        grow_account(
+            &project_info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            target_len,
+        )?;
+        let mut project = Project::try_deserialize(&mut &project_info.try_borrow_data()?[..])?;
+        require!(project.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+
+        project.token_decimals = ctx.accounts.token_mint_account.decimals;
+        if project.reward_mint.is_none() {
+            project.reward_decimals = project.token_decimals;
+        }
+        // What legacy stakes were distributed but not yet paid is unknown; reserve it all
+        project.total_reflections_unpaid = project.last_reflection_balance;
+        begin_legacy_migration(&mut project, Clock::get()?.unix_timestamp)?;
+        project.try_serialize(&mut &mut project_info.try_borrow_mut_data()?[..])?;
+
+        emit!(ProjectMigrated {
+            project: project_info.key(),
+        });
+
+        Ok(())
+    }
+
+    /// ADMIN: Reallocs a stake created before the current Stake layout and moves it
+    /// onto the current reward model. `legacy_credited_until` is when the old formula
+    /// last credited the stake without resetting last_stake_timestamp (a legacy partial
+    /// withdraw), or last_stake_timestamp if none did; the stake is credited under the
+    /// old formula from there to the project's migration cutoff.
+    /// remaining_accounts: the project's reward streams in index order
+    pub fn migrate_stake<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateStake<'info>>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        legacy_credited_until: i64,
+    ) -> Result<()> {
+        let stake_info = ctx.accounts.stake.to_account_info();
+        let target_len = 8 + Stake::INIT_SPACE;
+
+        require!(stake_info.owner == &crate::ID, ErrorCode::InvalidStakeAccount);
+        require!(stake_info.data_len() < target_len, ErrorCode::StakeAlreadyMigrated);
+
+        grow_account(
+            &stake_info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            target_len,
+        )?;
+        let mut stake = Stake::try_deserialize(&mut &stake_info.try_borrow_data()?[..])?;
+        require!(stake.project == ctx.accounts.project.key(), ErrorCode::InvalidProject);
+
+        let legacy_rewards = register_legacy_stake(&mut ctx.accounts.project, &mut stake, legacy_credited_until)?;
+
+        // Streams were added after the cutoff with this stake's weight already counted,
+        // so its zero-filled checkpoints earn each stream's whole history
+        let project_key = ctx.accounts.project.key();
+        settle_reward_streams(&project_key, &ctx.accounts.project, &mut stake, ctx.remaining_accounts)?;
+        stake.try_serialize(&mut &mut stake_info.try_borrow_mut_data()?[..])?;
+
+        emit!(StakeMigrated {
+            user: stake.user,
+            project: stake.project,
+            stake: stake_info.key(),
+            legacy_rewards,
+        });
+
+        Ok(())
+    }
+
+    pub fn refresh_reflections(
+        ctx: Context<RefreshReflections>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<()> {
+        ctx.accounts.validate()?;
+        require!(ctx.accounts.stake.amount > 0, ErrorCode::NoStake);
+        require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
+        require!(!ctx.accounts.project.claim_paused, ErrorCode::ClaimsPaused);
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_REFLECTIONS)?;
+        require!(ctx.accounts.project.enable_reflections, ErrorCode::ReflectionsNotEnabled);
+        require!(
+            ctx.accounts.project.reflection_token.is_some(),
+            ErrorCode::ReflectionTokenRequired
+        );
+
+        let project = &ctx.accounts.project;
+        let reflection_token_mint = project.reflection_token.unwrap();
+        
+        // Check if it's Native SOL
+        let native_sol = Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+        let is_native_sol = reflection_token_mint == native_sol;
+
+        msg!("🔄 Refreshing reflections:");
+        msg!("   User: {}", ctx.accounts.user.key());
+        msg!("   Reflection type: {}", if is_native_sol { "Native SOL" } else { "SPL Token" });
+        msg!("   Reflection token: {}", reflection_token_mint);
+
+        // Update reflection calculation - MUST update user paid marker to prevent double-counting
+        update_reflection_internal(
+            &mut ctx.accounts.project,
+            &mut ctx.accounts.stake,
+            Some(&ctx.accounts.reflection_vault.to_account_info()),
+            true,
+        )?;
+
+        msg!("✅ Reflections refreshed");
+        msg!("   Pending reflections: {}", ctx.accounts.stake.reflections_pending);
+        msg!("   Last balance: {}", ctx.accounts.project.last_reflection_balance);
+
+        Ok(())
+    }
+
+    pub fn deposit_rewards(
+        ctx: Context<DepositRewards>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        amount: u64
+    ) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidAmount);
+    require!(ctx.accounts.project.is_initialized, ErrorCode::NotInitialized);
+    require!(!ctx.accounts.project.is_paused, ErrorCode::ProjectPaused);
+    
+    let project_key = ctx.accounts.project.key();
+    
+    // ✅ Detect if Native SOL or SPL token
+    let is_native = is_native_sol(&ctx.accounts.token_mint_account.key());
+
+    // ✅ Get vault balance BEFORE transfer (handle both Native SOL and SPL)
+    let reward_vault_balance_before = if is_native {
+        ctx.accounts.reward_vault.lamports()
+    } else {
+        // For SPL tokens, read amount from TokenAccount data
+        let vault_data = ctx.accounts.reward_vault.try_borrow_data()?;
+        u64::from_le_bytes(vault_data[64..72].try_into().unwrap())
+    };
+
+    // ✅ Transfer rewards to vault (supports SPL, Token-2022, Native SOL)
+    // Tax will be applied automatically by token program if token has transfer tax
+    transfer_tokens(
+        ctx.accounts.admin_token_account.to_account_info(),
+        ctx.accounts.reward_vault.to_account_info(),
+        ctx.accounts.admin.to_account_info(),
+        &ctx.accounts.token_mint_account,
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        amount,
+        None,
+    )?;
+
+    // ✅ Get vault balance AFTER transfer (handle both Native SOL and SPL)
+    let reward_vault_balance_after = if is_native {
+        ctx.accounts.reward_vault.lamports()
+    } else {
+        // For SPL tokens, read amount from TokenAccount data
+        let vault_data = ctx.accounts.reward_vault.try_borrow_data()?;
+        u64::from_le_bytes(vault_data[64..72].try_into().unwrap())
+    };
+
+    let actual_received = reward_vault_balance_after
+        .checked_sub(reward_vault_balance_before)
+        .ok_or(ErrorCode::MathOverflow)?;
+    
+    msg!("💰 Admin deposited {} rewards, vault received {} (after any transfer tax)", amount, actual_received);
+    
+    let current_time = Clock::get()?.unix_timestamp;
+    let project_mut = &mut ctx.accounts.project;
+    require!(project_mut.rate_mode <= 1, ErrorCode::InvalidRateMode);
+    credit_reward_deposit(project_mut, actual_received, current_time)?;  // ✅ Use actual_received!
+    
+    // For fixed APY pools (rate_mode = 0), rate was already set in initialize_pool
+    if project_mut.rate_mode == 0 {
+        msg!("Fixed APY pool - rate: {}", project_mut.reward_rate_per_second);
+    } else {
+        msg!("Dynamic pool - new rate: {}", project_mut.reward_rate_per_second);
+    }
+    
+    emit!(RewardsDeposited {
+        project: project_key,
+        amount: actual_received,  // ✅ Emit actual received amount
+        total_rewards: project_mut.total_rewards_deposited,
+        reward_rate: project_mut.reward_rate_per_second,
+    });
+    
+    Ok(())
+}
+
+    pub fn update_referrer(
+        ctx: Context<UpdateReferrer>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        referrer: Option<Pubkey>,
+        split_bps: u64,
+    ) -> Result<()> {
+        require!(split_bps <= 10000, ErrorCode::InvalidSplitPercentage);
+        
+        let project = &mut ctx.accounts.project;
+        project.referrer = referrer;
+        project.referrer_split_bps = split_bps;
+        
+        emit!(ReferrerUpdated {
+            project: project.key(),
+            referrer,
+            split_bps,
+        });
+        
+        Ok(())
+    }
+
+    /// Queues a fee change - it can only be applied after FEE_CHANGE_DELAY_SECONDS
+    pub fn set_fees(
+        ctx: Context<SetFees>,
+        platform_token_fee_bps: u64,
+        platform_sol_fee: u64,
+    ) -> Result<()> {
+        validate_fees(platform_token_fee_bps, platform_sol_fee)?;
+
+        let effective_time = fee_change_effective_time(Clock::get()?.unix_timestamp)?;
+
+        let pending = &mut ctx.accounts.pending_fee_change;
+        pending.platform_token_fee_bps = platform_token_fee_bps;
+        pending.platform_sol_fee = platform_sol_fee;
+        pending.effective_time = effective_time;
+        pending.proposer = ctx.accounts.admin.key();
+        pending.bump = ctx.bumps.pending_fee_change;
+
+        emit!(FeeChangeQueued {
+            platform_token_fee_bps,
+            platform_sol_fee,
+            effective_time,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless - applies a queued fee change once its delay has passed
+    pub fn apply_fee_change(ctx: Context<ApplyFeeChange>) -> Result<()> {
+        let pending = &ctx.accounts.pending_fee_change;
+        require_fee_change_due(pending, Clock::get()?.unix_timestamp)?;
+
+        let platform_token_fee_bps = pending.platform_token_fee_bps;
+        let platform_sol_fee = pending.platform_sol_fee;
+
+        let platform = &mut ctx.accounts.platform;
+        platform.platform_token_fee_bps = platform_token_fee_bps;
+        platform.platform_sol_fee = platform_sol_fee;
+
+        emit!(FeesUpdated {
+            platform_token_fee_bps,
+            platform_sol_fee,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_fee_change(ctx: Context<CancelFeeChange>) -> Result<()> {
+        emit!(FeeChangeCancelled {
+            platform_token_fee_bps: ctx.accounts.pending_fee_change.platform_token_fee_bps,
+            platform_sol_fee: ctx.accounts.pending_fee_change.platform_sol_fee,
+        });
+
+        Ok(())
+    }
+
+    pub fn pause_project(
+        ctx: Context<PauseProject>,
+        _token_mint: Pubkey,
+        _pool_id: u64
+    ) -> Result<()> {
+        ctx.accounts.project.is_paused = true;
+        emit!(ProjectPaused { project: ctx.accounts.project.key() });
+        Ok(())
+    }
+
+    pub fn unpause_project(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64
+    ) -> Result<()> {
+        ctx.accounts.project.is_paused = false;
+        emit!(ProjectUnpaused { project: ctx.accounts.project.key() });
+        Ok(())
+    }
+
+    /// Sets (`paused = true`) or clears the deposit/withdraw/claim pause flags in `flags`.
+    /// Pausers may only set flags - clearing them requires the project admin.
+    pub fn set_pause_flags(
+        ctx: Context<PauseProject>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        flags: u8,
+        paused: bool,
+    ) -> Result<()> {
+        let is_admin = ctx.accounts.project.admin == ctx.accounts.admin.key();
+        let project = &mut ctx.accounts.project;
+        apply_pause_flags(project, flags, paused, is_admin)?;
+        let project_key = project.key();
+
+        if flags & PAUSE_DEPOSIT != 0 {
+            if paused {
+                emit!(DepositsPaused { project: project_key });
+            } else {
+                emit!(DepositsUnpaused { project: project_key });
+            }
+        }
+
+        if flags & PAUSE_WITHDRAW != 0 {
+            if paused {
+                emit!(WithdrawalsPaused { project: project_key });
+            } else {
+                emit!(WithdrawalsUnpaused { project: project_key });
+            }
+        }
+
+        if flags & PAUSE_CLAIM != 0 {
+            if paused {
+                emit!(ClaimsPaused { project: project_key });
+            } else {
+                emit!(ClaimsUnpaused { project: project_key });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// ADMIN: Allow users to pull their principal via emergency_withdraw
+    pub fn set_emergency_withdraw(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.project.emergency_withdraw_enabled = enabled;
+        emit!(EmergencyWithdrawToggled {
+            project: ctx.accounts.project.key(),
+            enabled,
+        });
+        Ok(())
+    }
+
+    /// Chooses how deposits are locked (LOCK_MODE_*). Only while nobody is staked,
+    /// so every stake's lots always cover its whole amount.
+    pub fn set_lock_mode(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        lock_mode: u8,
+    ) -> Result<()> {
+        let project = &mut ctx.accounts.project;
+        require!(lock_mode <= LOCK_MODE_WEIGHTED_AVERAGE, ErrorCode::InvalidLockMode);
+        require!(project.total_staked == 0, ErrorCode::LockModeFrozen);
+
+        project.lock_mode = lock_mode;
+        emit!(LockModeUpdated {
+            project: project.key(),
+            lock_mode,
+        });
+        Ok(())
+    }
+
+    /// Lets locked stake leave early for a penalty instead of failing with
+    /// LockupNotExpired. 0 bps keeps the hard lock.
+    pub fn set_early_exit_penalty(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        penalty_bps: u64,
+        decays: bool,
+        destination: u8,
+    ) -> Result<()> {
+        let project = &mut ctx.accounts.project;
+        validate_penalty_config(project, penalty_bps, destination)?;
+
+        project.early_exit_penalty_bps = penalty_bps;
+        project.early_exit_penalty_decays = decays;
+        project.penalty_destination = destination;
+
+        emit!(EarlyExitPenaltyUpdated {
+            project: project.key(),
+            penalty_bps,
+            decays,
+            destination,
+        });
+        Ok(())
+    }
+
+    pub fn set_compound_resets_lock(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        resets_lock: bool,
+    ) -> Result<()> {
+        ctx.accounts.project.compound_resets_lock = resets_lock;
+        emit!(CompoundLockConfigUpdated {
+            project: ctx.accounts.project.key(),
+            resets_lock,
+        });
+        Ok(())
+    }
+
+    /// Changes the APY of a fixed-rate pool. Only new deposits get the new rate;
+    /// existing stakes keep their snapshot until they top up or re-lock.
+    pub fn set_pool_rate(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        rate_bps_per_year: u64,
+    ) -> Result<()> {
+        let project = &mut ctx.accounts.project;
+        require!(project.is_initialized, ErrorCode::NotInitialized);
+        require!(project.rate_mode == 0, ErrorCode::InvalidRateMode);
+        require!(rate_bps_per_year <= 1_000_000, ErrorCode::InvalidRateBps);
+
+        update_pool_reward(project)?;
+
+        let old_rate_bps_per_year = project.rate_bps_per_year;
+        project.rate_bps_per_year = rate_bps_per_year;
+        project.reward_rate_per_second = fixed_rate_per_second(rate_bps_per_year)?;
+
+        emit!(PoolRateUpdated {
+            project: project.key(),
+            old_rate_bps_per_year,
+            rate_bps_per_year,
+            reward_rate_per_second: project.reward_rate_per_second,
+        });
+        Ok(())
+    }
+
+    /// Defines the pool's lock tiers. Tiers are frozen while anyone is staked so
+    /// existing locks and weights never change under users. An empty list falls
+    /// back to lockup_seconds at 1x.
+    pub fn set_lock_tiers(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        tiers: Vec<LockTier>,
+    ) -> Result<()> {
+        let project = &mut ctx.accounts.project;
+        require!(project.total_staked == 0, ErrorCode::LockTiersFrozen);
+        require!(tiers.len() <= MAX_LOCK_TIERS, ErrorCode::TooManyLockTiers);
+        for tier in tiers.iter() {
+            require!(
+                tier.multiplier_bps >= BASE_MULTIPLIER_BPS && tier.multiplier_bps <= MAX_LOCK_MULTIPLIER_BPS,
+                ErrorCode::InvalidLockMultiplier
+            );
+        }
+
+        project.lock_tiers = [LockTier::default(); MAX_LOCK_TIERS];
+        project.lock_tiers[..tiers.len()].copy_from_slice(&tiers);
+        project.lock_tier_count = tiers.len() as u8;
+
+        emit!(LockTiersUpdated {
+            project: project.key(),
+            tiers,
+        });
+        Ok(())
+    }
+
+    /// Moves pool_end_time of a running pool. Accrual is checkpointed first; variable
+    /// pools spread the still-unallocated rewards over the new window and fixed pools
+    /// must stay funded for the longer liability.
+    pub fn set_pool_end_time(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        new_end_time: i64,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let project = &mut ctx.accounts.project;
+        require!(project.is_initialized, ErrorCode::NotInitialized);
+
+        let old_end_time = project.pool_end_time;
+        reschedule_pool_end(project, new_end_time, current_time)?;
+
+        emit!(PoolEndTimeUpdated {
+            project: project.key(),
+            old_end_time,
+            new_end_time,
+            reward_rate_per_second: project.reward_rate_per_second,
+        });
+        Ok(())
+    }
+
+    /// VIEW: How many more tokens a fixed-APY pool can accept at its current rate
+    /// before funded rewards stop covering the projected liability.
+    pub fn remaining_capacity(
+        ctx: Context<RemainingCapacity>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<u64> {
+        let current_time = Clock::get()?.unix_timestamp;
+        fixed_pool_capacity(&ctx.accounts.project, current_time)
+    }
+
+    /// Returns the full principal and forfeits all pending rewards and reflections.
+    /// Reward settlement is best-effort and lockup and project pause are skipped,
+    /// so funds can always leave.
+    pub fn emergency_withdraw(
+        ctx: Context<EmergencyWithdraw>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.project.emergency_withdraw_enabled,
+            ErrorCode::EmergencyWithdrawDisabled
+        );
+        require_not_globally_paused(&ctx.accounts.platform, PAUSE_WITHDRAW)?;
+
+        // Principal still unbonding leaves too, without waiting for the cooldown
+        let amount = ctx.accounts.stake.amount
+            .checked_add(ctx.accounts.stake.unbonding_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(amount > 0, ErrorCode::NoStake);
+        require!(
+            ctx.accounts.staking_vault.amount >= amount,
+            ErrorCode::InsufficientVaultBalance
+        );
+        validate_withdrawal_account(
+            &ctx.accounts.withdrawal_token_account,
+            &ctx.accounts.token_program.key(),
+            &ctx.accounts.token_mint_account.key(),
+            &ctx.accounts.stake,
+        )?;
+
+        // remaining_accounts: the project's reward streams in index order. Best-effort like
+        // the pool rewards below: if the streams are missing or their math fails, the
+        // stake's stream rewards are dropped and stay reserved in each stream.
+        let project_key = ctx.accounts.project.key();
+        if forfeit_reward_streams(&project_key, &ctx.accounts.project, &mut ctx.accounts.stake, ctx.remaining_accounts).is_err() {
+            for checkpoint in ctx.accounts.stake.stream_checkpoints.iter_mut() {
+                checkpoint.rewards_pending = 0;
+            }
+        }
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let (rewards_forfeited, reflections_forfeited) =
+            forfeit_stake_rewards(&mut ctx.accounts.project, &mut ctx.accounts.stake, current_time);
+
+        release_stake_principal(&mut ctx.accounts.project, &mut ctx.accounts.stake, current_time)?;
+
+        let seeds = project_signer_seeds!(ctx.accounts.project);
+        let signer = &[&seeds[..]];
+
+        transfer_tokens(
+            ctx.accounts.staking_vault.to_account_info(),
+            ctx.accounts.withdrawal_token_account.to_account_info(),
+            ctx.accounts.project.to_account_info(),
+            &ctx.accounts.token_mint_account,
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            amount,
+            Some(signer),
+        )?;
+
+        emit!(EmergencyWithdrawn {
+            user: ctx.accounts.user.key(),
+            project: ctx.accounts.project.key(),
+            amount,
+            rewards_forfeited,
+            reflections_forfeited,
+        });
+
+        Ok(())
+    }
+
+    /// ADMIN: Push principal back to stakers' withdrawal wallets.
+    /// Earned rewards and reflections stay pending; claim and claim_reflections
+    /// still pay them once the principal is gone.
+    /// remaining_accounts: optional [stake, withdrawal_token_account] pairs for batch refunds,
+    /// followed by the project's reward streams in index order
+    pub fn emergency_return_stake<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmergencyReturnStake<'info>>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.stake.amount > 0 || ctx.accounts.stake.unbonding_amount > 0,
+            ErrorCode::NoStake
+        );
+        let (pairs, stream_accounts) = split_remaining_accounts(&ctx.accounts.project, ctx.remaining_accounts)?;
+        require!(
+            pairs.len() % 2 == 0,
+            ErrorCode::InvalidRemainingAccounts
+        );
+        let project_key = ctx.accounts.project.key();
+
+        let reflection_vault = if ctx.accounts.project.enable_reflections {
+            let vault = match ctx.accounts.reflection_vault.as_ref() {
+                Some(vault) => vault.to_account_info(),
+                None => ctx.accounts.project.to_account_info(),
+            };
+            require!(
+                ctx.accounts.project.reflection_vault == Some(vault.key()),
+                ErrorCode::InvalidReflectionVault
+            );
+            Some(vault)
+        } else {
+            None
+        };
+
+        let staking_vault = ctx.accounts.staking_vault.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let system_program = ctx.accounts.system_program.to_account_info();
+
+        if let Some(vault) = reflection_vault.as_ref() {
+            update_reflection(&mut ctx.accounts.project, &mut ctx.accounts.stake, Some(vault))?;
+        }
+        settle_reward_streams(&project_key, &ctx.accounts.project, &mut ctx.accounts.stake, stream_accounts)?;
+        return_stake_principal(
+            &mut ctx.accounts.project,
+            &mut ctx.accounts.stake,
+            &ctx.accounts.withdrawal_token_account,
+            &staking_vault,
+            &ctx.accounts.token_mint_account,
+            &token_program,
+            &system_program,
+        )?;
+
+        for pair in pairs.chunks(2) {
+            let mut stake: Account<'info, Stake> = Account::try_from(&pair[0])?;
+            require!(stake.project == ctx.accounts.project.key(), ErrorCode::InvalidProject);
+            require!(pair[0].key() != ctx.accounts.stake.key(), ErrorCode::InvalidStakeAccount);
+
+            if stake.amount == 0 && stake.unbonding_amount == 0 {
+                continue;
+            }
+
+            if let Some(vault) = reflection_vault.as_ref() {
+                update_reflection(&mut ctx.accounts.project, &mut stake, Some(vault))?;
+            }
+            settle_reward_streams(&project_key, &ctx.accounts.project, &mut stake, stream_accounts)?;
+            return_stake_principal(
+                &mut ctx.accounts.project,
+                &mut stake,
+                &pair[1],
+                &staking_vault,
+                &ctx.accounts.token_mint_account,
+                &token_program,
+                &system_program,
+            )?;
+
+            stake.exit(&crate::ID)?;
+        }
+
+        Ok(())
+    }
+
+    /// ADMIN: Cooldown between request_unstake and complete_unstake, at most
+    /// MAX_UNBONDING_SECONDS. While non-zero, withdraw and exit are disabled and
+    /// principal leaves only through the queue. Queued requests keep the ready time
+    /// they were given.
+    pub fn set_unbonding_period(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        unbonding_seconds: u64,
+    ) -> Result<()> {
+        require!(unbonding_seconds <= MAX_UNBONDING_SECONDS, ErrorCode::InvalidUnbondingPeriod);
+        ctx.accounts.project.unbonding_seconds = unbonding_seconds;
+        emit!(UnbondingPeriodUpdated {
+            project: ctx.accounts.project.key(),
+            unbonding_seconds,
+        });
+        Ok(())
+    }
+
+    /// ADMIN: Delay applied to change_withdrawal_wallet (0 = immediate)
+    pub fn set_withdrawal_wallet_delay(
+        ctx: Context<PauseControl>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        delay_seconds: u64,
+    ) -> Result<()> {
+        require!(delay_seconds <= i64::MAX as u64, ErrorCode::MathOverflow);
+        ctx.accounts.project.withdrawal_wallet_delay_seconds = delay_seconds;
+        emit!(WithdrawalWalletDelayUpdated {
+            project: ctx.accounts.project.key(),
+            delay_seconds,
+        });
+        Ok(())
+    }
+
+    /// Sets the wallet that withdrawals and claims pay out to.
+    /// Applied immediately, or queued if the project has a withdrawal wallet delay.
+    pub fn change_withdrawal_wallet(
+        ctx: Context<ChangeWithdrawalWallet>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        new_wallet: Pubkey,
+    ) -> Result<()> {
+        require!(new_wallet != Pubkey::default(), ErrorCode::InvalidWithdrawalWallet);
+
+        let delay = ctx.accounts.project.withdrawal_wallet_delay_seconds;
+        let stake = &mut ctx.accounts.stake;
+
+        if delay == 0 {
+            stake.withdrawal_wallet = new_wallet;
+            stake.pending_withdrawal_wallet = None;
+            stake.withdrawal_wallet_effective_time = 0;
+
+            emit!(WithdrawalWalletChanged {
+                user: stake.user,
+                project: stake.project,
+                new_wallet,
+            });
+        } else {
+            let effective_time = Clock::get()?.unix_timestamp
+                .checked_add(delay as i64)
+                .ok_or(ErrorCode::MathOverflow)?;
+
+            stake.pending_withdrawal_wallet = Some(new_wallet);
+            stake.withdrawal_wallet_effective_time = effective_time;
+
+            emit!(WithdrawalWalletChangeQueued {
+                user: stake.user,
+                project: stake.project,
+                new_wallet,
+                effective_time,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_withdrawal_wallet_change(
+        ctx: Context<ChangeWithdrawalWallet>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<()> {
+        let stake = &mut ctx.accounts.stake;
+        let new_wallet = stake.pending_withdrawal_wallet
+            .ok_or(ErrorCode::NoPendingWithdrawalWallet)?;
+        require!(
+            Clock::get()?.unix_timestamp >= stake.withdrawal_wallet_effective_time,
+            ErrorCode::WithdrawalWalletChangeNotReady
+        );
+
+        stake.withdrawal_wallet = new_wallet;
+        stake.pending_withdrawal_wallet = None;
+        stake.withdrawal_wallet_effective_time = 0;
+
+        emit!(WithdrawalWalletChanged {
+            user: stake.user,
+            project: stake.project,
+            new_wallet,
+        });
+
+        Ok(())
+    }
+
+    pub fn emergency_unlock(
+        ctx: Context<EmergencyUnlockAccounts>,
+        _token_mint: Pubkey,
+        _pool_id: u64
+    ) -> Result<()> {
+        let project = &mut ctx.accounts.project;
+        project.lockup_seconds = 0;
+        for tier in project.lock_tiers.iter_mut() {
+            tier.lockup_seconds = 0;
+        }
+        project.locks_released = true;
+        emit!(EmergencyUnlockEvent { 
+            project: ctx.accounts.project.key(),
+            admin: ctx.accounts.admin.key(),
+        });
+        Ok(())
+    }
+
+    /// ADMIN: Sweep surplus from a project vault. Only tokens not owed to stakers can leave:
+    /// staking vault - balance above total_staked and total_unbonding, reward vault - balance above outstanding
+    /// accrued rewards and rewards still scheduled to accrue until pool end,
+    /// reflection vault - balance neither distributed to stakers nor waiting for the next refresh.
+    pub fn claim_unclaimed_tokens(
+        ctx: Context<ClaimUnclaimedTokens>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        amount: u64
+    ) -> Result<()> {
+        ctx.accounts.validate()?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let vault_key = ctx.accounts.vault.key();
+        let is_staking_vault = vault_key == ctx.accounts.project.staking_vault;
+        let is_reward_vault = vault_key == ctx.accounts.project.reward_vault;
+
+        let expected_mint = if is_staking_vault {
+            ctx.accounts.project.token_mint
+        } else if is_reward_vault {
+            ctx.accounts.project.reward_mint_key()
+        } else {
+            ctx.accounts.project.reflection_token.ok_or(ErrorCode::ReflectionTokenRequired)?
+        };
+        require!(
+            ctx.accounts.token_mint_account.key() == expected_mint,
+            ErrorCode::WrongTokenType
+        );
+
+        let is_native = is_native_sol(&ctx.accounts.token_mint_account.key());
+        let balance = vault_balance(&ctx.accounts.vault, is_native)?;
+
+        let surplus = if is_staking_vault {
+            balance
+                .saturating_sub(ctx.accounts.project.total_staked)
+                .saturating_sub(ctx.accounts.project.total_unbonding)
+        } else if is_reward_vault {
+            update_pool_reward(&mut ctx.accounts.project)?;
+            let outstanding = projected_liability(&ctx.accounts.project)?
+                .saturating_sub(ctx.accounts.project.total_rewards_claimed);
+            balance.saturating_sub(outstanding)
+        } else {
+            reflection_surplus(&ctx.accounts.project, balance)
+        };
+
+        require!(amount <= surplus, ErrorCode::AmountExceedsSurplus);
+
+        // Swept rewards no longer count towards funding
+        if is_reward_vault {
+            let project = &mut ctx.accounts.project;
+            project.total_rewards_deposited = project.total_rewards_deposited.saturating_sub(amount);
+        } else if !is_staking_vault {
+            // Lower the baseline so reflections that arrived meanwhile are still distributed
+            let project = &mut ctx.accounts.project;
+            project.last_reflection_balance = project.last_reflection_balance.saturating_sub(amount);
+        }
+
+        let project = &ctx.accounts.project;
+        let seeds = project_signer_seeds!(project);
+        let signer = &[&seeds[..]];
+        
+        // ✅ Transfer tokens to admin (supports SPL, Token-2022, Native SOL)
+        transfer_tokens(
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.admin_token_account.to_account_info(),
+            ctx.accounts.project.to_account_info(),
+            &ctx.accounts.token_mint_account,
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            amount,
+            Some(signer),
+        )?;
+
+        emit!(UnclaimedTokensSwept {
+            project: ctx.accounts.project.key(),
+            vault: vault_key,
+            admin: ctx.accounts.admin.key(),
+            amount,
+        });
+        
+        Ok(())
+    }
+
+    /// remaining_accounts: [reward_stream, stream_vault, admin_stream_account, stream_mint,
+    /// stream_token_program] for each reward stream in index order; the stream vaults are
+    /// swept to the admin and closed along with the streams
+    pub fn close_project<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseProject<'info>>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<()> {
+        ctx.accounts.validate()?;
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require_project_closable(&ctx.accounts.project, current_time)?;
+
+        let project_info = ctx.accounts.project.to_account_info();
+        let admin_info = ctx.accounts.admin.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+
+        let staking_swept = sweep_and_close_vault(
+            &ctx.accounts.project,
+            &project_info,
+            &ctx.accounts.staking_vault.to_account_info(),
+            &ctx.accounts.admin_token_account,
+            &admin_info,
+            &ctx.accounts.token_mint_account,
+            &token_program,
+        )?;
+
+        let rewards_swept = sweep_and_close_vault(
+            &ctx.accounts.project,
+            &project_info,
+            &ctx.accounts.reward_vault.to_account_info(),
+            &ctx.accounts.admin_reward_account,
+            &admin_info,
+            &ctx.accounts.reward_mint_account,
+            &ctx.accounts.reward_token_program.to_account_info(),
+        )?;
+
+        let project_key = ctx.accounts.project.key();
+        require!(
+            ctx.remaining_accounts.len() == ctx.accounts.project.reward_stream_count as usize * 5,
+            ErrorCode::MissingRewardStreams
+        );
+        for (index, accounts) in ctx.remaining_accounts.chunks(5).enumerate() {
+            let [stream_info, stream_vault, admin_stream_account, stream_mint, stream_token_program] = accounts else {
+                return Err(ErrorCode::MissingRewardStreams.into());
+            };
+
+            let stream = load_reward_stream(stream_info, &project_key, index as u8)?;
+            require!(stream.total_pending == 0, ErrorCode::RewardsOutstanding);
+            require!(stream_vault.key() == stream.vault, ErrorCode::InvalidRewardVault);
+            require!(stream_mint.key() == stream.mint, ErrorCode::WrongTokenType);
+            validate_token_account(stream_vault, stream_token_program.key, &stream.mint, &project_key)?;
+            validate_wallet_account(
+                admin_stream_account,
+                stream_token_program.key,
+                &stream.mint,
+                &ctx.accounts.admin.key(),
+            )?;
+
+            let mint: InterfaceAccount<'info, Mint> = InterfaceAccount::try_from(stream_mint)?;
+            let stream_swept = sweep_and_close_vault(
+                &ctx.accounts.project,
+                &project_info,
+                stream_vault,
+                admin_stream_account,
+                &admin_info,
+                &mint,
+                stream_token_program,
+            )?;
+
+            let stream_account: Account<'info, RewardStream> = Account::try_from(stream_info)?;
+            stream_account.close(admin_info.clone())?;
+
+            emit!(UnclaimedTokensSwept {
+                project: project_key,
+                vault: stream.vault,
+                admin: ctx.accounts.admin.key(),
+                amount: stream_swept,
+            });
+        }
+
+        // Native SOL reflections live in the Project PDA and leave with its lamports
+        let reflection_vault_key = ctx.accounts.project.reflection_vault;
+        let reflections_swept = match reflection_vault_key {
+            Some(vault_key) if vault_key != ctx.accounts.project.key() => {
+                let (Some(reflection_vault), Some(admin_reflection_account), Some(reflection_token_mint), Some(reflection_token_program)) = (
+                    ctx.accounts.reflection_vault.as_ref(),
+                    ctx.accounts.admin_reflection_account.as_ref(),
+                    ctx.accounts.reflection_token_mint.as_ref(),
+                    ctx.accounts.reflection_token_program.as_ref(),
+                ) else {
+                    return Err(ErrorCode::ReflectionAccountsRequired.into());
+                };
+
+                require!(
+                    Some(reflection_token_mint.key()) == ctx.accounts.project.reflection_token,
+                    ErrorCode::WrongTokenType
+                );
+                validate_reflection_vault(&ctx.accounts.project, Some(reflection_vault))?;
+                validate_wallet_account(
+                    admin_reflection_account,
+                    &reflection_token_program.key(),
+                    &reflection_token_mint.key(),
+                    &ctx.accounts.admin.key(),
+                )?;
+
+                sweep_and_close_vault(
+                    &ctx.accounts.project,
+                    &project_info,
+                    reflection_vault,
+                    admin_reflection_account,
+                    &admin_info,
+                    reflection_token_mint,
+                    &reflection_token_program.to_account_info(),
+                )?
+            }
+            _ => 0,
+        };
+
+        emit!(ProjectClosed {
+            project: ctx.accounts.project.key(),
+            admin: ctx.accounts.admin.key(),
+            staking_swept,
+            rewards_swept,
+            reflections_swept,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_fee_collector(
+        ctx: Context<UpdateFeeCollector>,
+        new_fee_collector: Pubkey,
+    ) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        let old_fee_collector = platform.fee_collector;
+        platform.fee_collector = new_fee_collector;
+        
+        emit!(FeeCollectorUpdated {
+            old_fee_collector,
+            new_fee_collector,
+        });
+        
+        Ok(())
+    }
+
+    /// Step 1 of the platform admin handover - records the proposed admin
+    pub fn propose_platform_admin(
+        ctx: Context<ProposePlatformAdmin>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        require!(new_admin != Pubkey::default(), ErrorCode::InvalidPendingAdmin);
+
+        let platform = &mut ctx.accounts.platform;
+        platform.pending_admin = Some(new_admin);
+
+        emit!(AdminProposed {
+            account: platform.key(),
+            current_admin: platform.admin,
+            pending_admin: new_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Step 2 of the platform admin handover - must be signed by the pending admin
+    pub fn accept_platform_admin(ctx: Context<AcceptPlatformAdmin>) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        let old_admin = platform.admin;
+
+        platform.admin = ctx.accounts.new_admin.key();
+        platform.pending_admin = None;
+
+        emit!(AdminAccepted {
+            account: platform.key(),
+            old_admin,
+            new_admin: platform.admin,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_platform_admin_proposal(ctx: Context<ProposePlatformAdmin>) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        let cancelled_admin = platform.pending_admin
+            .ok_or(ErrorCode::NoPendingAdmin)?;
+
+        platform.pending_admin = None;
+
+        emit!(AdminProposalCancelled {
+            account: platform.key(),
+            cancelled_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Step 1 of the project admin handover - records the proposed admin
+    pub fn propose_project_admin(
+        ctx: Context<ProposeProjectAdmin>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        require!(new_admin != Pubkey::default(), ErrorCode::InvalidPendingAdmin);
+
+        let project = &mut ctx.accounts.project;
+        project.pending_admin = Some(new_admin);
+
+        emit!(AdminProposed {
+            account: project.key(),
+            current_admin: project.admin,
+            pending_admin: new_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Step 2 of the project admin handover - must be signed by the pending admin
+    pub fn accept_project_admin(
+        ctx: Context<AcceptProjectAdmin>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<()> {
+        let project = &mut ctx.accounts.project;
+        let old_admin = project.admin;
+
+        project.admin = ctx.accounts.new_admin.key();
+        project.pending_admin = None;
+
+        emit!(AdminAccepted {
+            account: project.key(),
+            old_admin,
+            new_admin: project.admin,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_project_admin_proposal(
+        ctx: Context<ProposeProjectAdmin>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+    ) -> Result<()> {
+        let project = &mut ctx.accounts.project;
+        let cancelled_admin = project.pending_admin
+            .ok_or(ErrorCode::NoPendingAdmin)?;
+
+        project.pending_admin = None;
+
+        emit!(AdminProposalCancelled {
+            account: project.key(),
+            cancelled_admin,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the platform-wide pause bitmask (PAUSE_* flags).
+    /// Guardians may only add flags - clearing them requires the platform admin.
+    pub fn set_global_pause(
+        ctx: Context<SetGlobalPause>,
+        flags: u8,
+        reason_code: u16,
+    ) -> Result<()> {
+        let platform = &mut ctx.accounts.platform;
+        let is_admin = platform.admin == ctx.accounts.authority.key();
+        platform.global_pause = next_global_pause(platform.global_pause, flags, is_admin)?;
+
+        emit!(PlatformPaused {
+            global_pause: flags,
+            reason_code,
+            authority: ctx.accounts.authority.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn grant_platform_role(
+        ctx: Context<GrantPlatformRole>,
+        role: u8,
+        member: Pubkey,
+    ) -> Result<()> {
+        require_grantable_role(role, PLATFORM_ROLES)?;
+
+        let target = ctx.accounts.platform.key();
+        let registry = &mut ctx.accounts.role_registry;
+        registry.target = target;
+        registry.bump = ctx.bumps.role_registry;
+        registry.grant(member, role)?;
+
+        emit!(RoleGranted { target, member, role });
+
+        Ok(())
+    }
+
+    pub fn revoke_platform_role(
+        ctx: Context<RevokePlatformRole>,
+        role: u8,
+        member: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.role_registry.revoke(&member, role)?;
+
+        emit!(RoleRevoked {
+            target: ctx.accounts.platform.key(),
+            member,
+            role,
+        });
+
+        Ok(())
+    }
+
+    pub fn grant_project_role(
+        ctx: Context<GrantProjectRole>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        role: u8,
+        member: Pubkey,
+    ) -> Result<()> {
+        require_grantable_role(role, PROJECT_ROLES)?;
+
+        let target = ctx.accounts.project.key();
+        let registry = &mut ctx.accounts.role_registry;
+        registry.target = target;
+        registry.bump = ctx.bumps.role_registry;
+        registry.grant(member, role)?;
+
+        emit!(RoleGranted { target, member, role });
+
+        Ok(())
+    }
+
+    pub fn revoke_project_role(
+        ctx: Context<RevokeProjectRole>,
+        _token_mint: Pubkey,
+        _pool_id: u64,
+        role: u8,
+        member: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.role_registry.revoke(&member, role)?;
+
+        emit!(RoleRevoked {
+            target: ctx.accounts.project.key(),
+            member,
+            role,
+        });
+
+        Ok(())
+    }
+}
+
+fn validate_fees(platform_token_fee_bps: u64, platform_sol_fee: u64) -> Result<()> {
+    require!(
+        platform_token_fee_bps <= MAX_PLATFORM_TOKEN_FEE_BPS,
+        ErrorCode::TokenFeeTooHigh
+    );
+    require!(
+        platform_sol_fee <= MAX_PLATFORM_SOL_FEE,
+        ErrorCode::SolFeeTooHigh
+    );
+    Ok(())
+}
+
+// When a fee change queued at `now` becomes applicable
+fn fee_change_effective_time(now: i64) -> Result<i64> {
+    Ok(now
+        .checked_add(FEE_CHANGE_DELAY_SECONDS)
+        .ok_or(ErrorCode::MathOverflow)?)
+}
+
+fn require_fee_change_due(pending: &PendingFeeChange, now: i64) -> Result<()> {
+    require!(now >= pending.effective_time, ErrorCode::FeeChangeNotReady);
+    Ok(())
+}
+
+// Collects the platform SOL fee from the user, splitting it with the project
+// referrer when the referrer account is passed as remaining_accounts[0]
+fn collect_sol_fee<'info>(
+    project: &Project,
+    platform_sol_fee: u64,
+    user: &AccountInfo<'info>,
+    fee_collector: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    system_program_account: &AccountInfo<'info>,
+) -> Result<()> {
+    if platform_sol_fee == 0 {
+        return Ok(());
+    }
+
+    let referrer_account = match project.referrer {
+        Some(_) => remaining_accounts.first(),
+        None => None,
+    };
+
+    let referrer_amount = if let Some(referrer_account) = referrer_account {
+        require!(
+            Some(referrer_account.key()) == project.referrer,
+            ErrorCode::InvalidReferrer
+        );
+        platform_sol_fee
+            .checked_mul(project.referrer_split_bps)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)?
+    } else {
+        0
+    };
+
+    let admin_amount = platform_sol_fee
+        .checked_sub(referrer_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    system_program::transfer(
+        CpiContext::new(
+            system_program_account.clone(),
+            system_program::Transfer {
+                from: user.clone(),
+                to: fee_collector.clone(),
+            },
+        ),
+        admin_amount,
+    )?;
+
+    if let Some(referrer_account) = referrer_account {
+        if referrer_amount > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program_account.clone(),
+                    system_program::Transfer {
+                        from: user.clone(),
+                        to: referrer_account.clone(),
+                    },
+                ),
+                referrer_amount,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// Principal the stake may withdraw right now under the pool's lock mode
+fn unlocked_amount(project: &Project, stake: &Stake, current_time: i64) -> Result<u64> {
+    if project.locks_released {
+        return Ok(stake.amount);
+    }
+
+    if project.lock_mode == LOCK_MODE_RELOCK {
+        let time_staked = current_time
+            .checked_sub(stake.last_stake_timestamp)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        require!(time_staked >= 0, ErrorCode::InvalidTimestamp);
+        let expired = time_staked >= project.tier_lockup_seconds(stake.lock_tier) as i64;
+        return Ok(if expired { stake.amount } else { 0 });
+    }
+
+    Ok(stake.lots[..stake.lot_count as usize]
+        .iter()
+        .filter(|lot| lot.unlock_time <= current_time)
+        .map(|lot| lot.amount)
+        .sum())
+}
+
+// Fails with LockupNotExpired unless `amount` of the stake is unlocked
+fn require_unlocked(project: &Project, stake: &Stake, amount: u64, current_time: i64) -> Result<()> {
+    require!(
+        amount <= unlocked_amount(project, stake, current_time)?,
+        ErrorCode::LockupNotExpired
+    );
+    Ok(())
+}
+
+// Penalty for taking `amount` out of the stake now. Locked principal is priced lot by
+// lot in the order consume_lots takes it; LockupNotExpired if the pool has no penalty.
+fn early_exit_penalty(project: &Project, stake: &Stake, amount: u64, current_time: i64) -> Result<u64> {
+    let unlocked = unlocked_amount(project, stake, current_time)?;
+    if amount <= unlocked {
+        return Ok(0);
+    }
+    require!(project.early_exit_penalty_bps > 0, ErrorCode::LockupNotExpired);
+
+    let lockup_seconds = project.tier_lockup_seconds(stake.lock_tier);
+    let relock = [StakeLot {
+        amount: stake.amount,
+        unlock_time: stake.last_stake_timestamp.saturating_add(lockup_seconds as i64),
+    }];
+    let lots = if project.lock_mode == LOCK_MODE_RELOCK {
+        &relock[..]
+    } else {
+        &stake.lots[..stake.lot_count as usize]
+    };
+
+    let mut locked_to_take = amount - unlocked;
+    let mut penalty = 0u128;
+    for lot in lots.iter().filter(|lot| lot.unlock_time > current_time) {
+        if locked_to_take == 0 {
+            break;
+        }
+        let taken = lot.amount.min(locked_to_take);
+        locked_to_take -= taken;
+        penalty += taken as u128 * penalty_bps_at(project, lot.unlock_time, lockup_seconds, current_time) as u128;
+    }
+
+    Ok((penalty / 10_000) as u64)
+}
+
+// Penalty rate for principal unlocking at `unlock_time`, optionally decaying
+// linearly to zero over the lockup
+fn penalty_bps_at(project: &Project, unlock_time: i64, lockup_seconds: u64, current_time: i64) -> u64 {
+    if !project.early_exit_penalty_decays || lockup_seconds == 0 {
+        return project.early_exit_penalty_bps;
+    }
+    let remaining = unlock_time
+        .saturating_sub(current_time)
+        .clamp(0, lockup_seconds as i64) as u128;
+    (project.early_exit_penalty_bps as u128 * remaining / lockup_seconds as u128) as u64
+}
+
+fn validate_penalty_config(project: &Project, penalty_bps: u64, destination: u8) -> Result<()> {
+    require!(penalty_bps <= MAX_EARLY_EXIT_PENALTY_BPS, ErrorCode::InvalidPenaltyBps);
+    require!(destination <= PENALTY_TO_FEE_COLLECTOR, ErrorCode::InvalidPenaltyDestination);
+    if destination == PENALTY_TO_BURN {
+        require!(!is_native_sol(&project.token_mint), ErrorCode::NativePenaltyBurn);
+    }
+    if destination == PENALTY_TO_REWARD_VAULT || destination == PENALTY_TO_STAKERS {
+        require!(
+            project.reward_mint_key() == project.token_mint,
+            ErrorCode::PenaltyRequiresSameMint
+        );
+    }
+    Ok(())
+}
+
+// Books a penalty moved into the reward vault. Redistributed penalties are paid out
+// at once to the remaining weight through the accumulator; otherwise the penalty
+// funds future rewards exactly like deposit_rewards.
+fn credit_penalty(project: &mut Project, amount: u64, to_stakers: bool, current_time: i64) -> Result<()> {
+    if !to_stakers || project.total_weight == 0 {
+        return credit_reward_deposit(project, amount, current_time);
+    }
+
+    project.total_rewards_deposited = project.total_rewards_deposited
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.acc_reward_per_token = project.acc_reward_per_token
+        .checked_add(reward_per_token_delta(amount, 1, project.total_weight)?)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.total_rewards_accrued = project.total_rewards_accrued
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    project.projected_liability = projected_liability(project)?;
+    Ok(())
+}
+
+// Latest unlock time among the stake's lots (0 without lots)
+fn latest_unlock_time(stake: &Stake) -> i64 {
+    stake.lots[..stake.lot_count as usize]
+        .iter()
+        .map(|lot| lot.unlock_time)
+        .max()
+        .unwrap_or(0)
+}
+
+// Records a deposit's unlock time. Lots mode appends a lot, merging into the newest
+// one when it unlocks no earlier or the list is full; weighted-average mode keeps a
+// single lot whose unlock time is averaged by amount.
+fn lock_deposit(project: &Project, stake: &mut Stake, amount: u64, unlock_time: i64) -> Result<()> {
+    let count = stake.lot_count as usize;
+    match project.lock_mode {
+        LOCK_MODE_LOTS => {
+            if count > 0 && (count == MAX_STAKE_LOTS || stake.lots[count - 1].unlock_time >= unlock_time) {
+                let lot = &mut stake.lots[count - 1];
+                lot.amount = lot.amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+                lot.unlock_time = lot.unlock_time.max(unlock_time);
+            } else {
+                stake.lots[count] = StakeLot { amount, unlock_time };
+                stake.lot_count += 1;
+            }
+        }
+        LOCK_MODE_WEIGHTED_AVERAGE => {
+            let lot = &mut stake.lots[0];
+            let total = lot.amount.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+            if total > 0 {
+                let weighted = (lot.amount as i128 * lot.unlock_time as i128
+                    + amount as i128 * unlock_time as i128)
+                    / total as i128;
+                lot.unlock_time = weighted as i64;
+            }
+            lot.amount = total;
+            stake.lot_count = 1;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Moves an existing stake onto another tier. Locks can only get longer, and since the
+// new multiplier boosts the whole position, every lot takes the new tier's lock too
+// (relock mode restarts the lock through last_stake_timestamp instead).
+fn switch_lock_tier(project: &Project, stake: &mut Stake, lock_tier: u8, current_time: i64) -> Result<()> {
+    if lock_tier == stake.lock_tier {
+        return Ok(());
+    }
+
+    let tier = project.lock_tier_terms(lock_tier)?;
+    if let Ok(current) = project.lock_tier_terms(stake.lock_tier) {
+        require!(tier.lockup_seconds >= current.lockup_seconds, ErrorCode::LockTierDowngrade);
+    }
+    stake.lock_tier = lock_tier;
+
+    let unlock_time = current_time
+        .checked_add(tier.lockup_seconds as i64)
+        .ok_or(ErrorCode::MathOverflow)?;
+    for lot in stake.lots[..stake.lot_count as usize].iter_mut() {
+        lot.unlock_time = lot.unlock_time.max(unlock_time);
+    }
+    Ok(())
+}
+
+// Takes `amount` out of the stake's lots: unlocked lots first, oldest first, then
+// locked lots oldest first (only reached by paths that bypass the lock)
+fn consume_lots(stake: &mut Stake, amount: u64, current_time: i64) {
+    let count = stake.lot_count as usize;
+    let mut remaining = amount;
+    for unlocked_pass in [true, false] {
+        for lot in stake.lots[..count].iter_mut() {
+            if remaining == 0 {
+                break;
+            }
+            if (lot.unlock_time <= current_time) != unlocked_pass {
+                continue;
+            }
+            let taken = lot.amount.min(remaining);
+            lot.amount -= taken;
+            remaining -= taken;
+        }
+    }
+
+    // Drop emptied lots, keeping the rest in deposit order
+    let mut kept = 0;
+    for i in 0..count {
+        if stake.lots[i].amount > 0 {
+            stake.lots[kept] = stake.lots[i];
+            kept += 1;
+        }
+    }
+    for lot in stake.lots[kept..].iter_mut() {
+        *lot = StakeLot::default();
+    }
+    stake.lot_count = kept as u8;
+}
+
+// Pays a stake's pending reflections from the reflection vault and resyncs the
+// project's last reflection balance. Native SOL keeps a 0.3% buffer for rent.
+fn pay_reflections<'info>(
+    project: &mut Project,
+    project_info: &AccountInfo<'info>,
+    stake: &mut Stake,
+    reflection_vault: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    reflection_mint: &InterfaceAccount<'info, Mint>,
+    token_program: &AccountInfo<'info>,
+) -> Result<u64> {
+    let amount = stake.reflections_pending;
+    let is_native = is_native_sol(&reflection_mint.key());
+
+    // ✅ Apply 99.7% buffer for Native SOL to prevent rent issues
+    let amount_to_transfer = if is_native {
+        amount.saturating_mul(997).saturating_div(1000)
+    } else {
+        amount
+    };
+
+    if is_native {
+        // Reflection vault is the Project PDA itself - move lamports directly
+        **reflection_vault.try_borrow_mut_lamports()? -= amount_to_transfer;
+        **destination.try_borrow_mut_lamports()? += amount_to_transfer;
+    } else {
+        let seeds = project_signer_seeds!(project);
+        transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                TransferChecked {
+                    from: reflection_vault.clone(),
+                    to: destination.clone(),
+                    authority: project_info.clone(),
+                    mint: reflection_mint.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            amount_to_transfer,
+            reflection_mint.decimals,
+        )?;
+    }
+
+    stake.reflections_pending = 0;
+    stake.total_reflections_claimed = stake.total_reflections_claimed
+        .checked_add(amount_to_transfer)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.total_reflections_pending = project.total_reflections_pending.saturating_sub(amount);
+    project.total_reflections_unpaid = project.total_reflections_unpaid.saturating_sub(amount);
+
+    // Update last balance to current vault balance
+    project.last_reflection_balance = if is_native {
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(reflection_vault.data_len());
+        let fixed_buffer = rent_exempt_minimum.saturating_add(3_000_000);
+        reflection_vault.lamports().saturating_sub(fixed_buffer)
+    } else {
+        let vault_data = reflection_vault.try_borrow_data()?;
+        if vault_data.len() >= 72 {
+            u64::from_le_bytes(vault_data[64..72].try_into().unwrap())
+        } else {
+            0
+        }
+    };
+
+    Ok(amount_to_transfer)
+}
+
+// close_project may only run once the pool has ended and owes stakers nothing
+fn require_project_closable(project: &Project, current_time: i64) -> Result<()> {
+    require!(current_time >= project.pool_end_time, ErrorCode::PoolNotEnded);
+    require!(
+        project.total_staked == 0 && project.total_unbonding == 0,
+        ErrorCode::PoolNotEmpty
+    );
+    // The vaults and the Project's lamports still back what stakers can claim
+    require!(
+        project.total_rewards_pending == 0 && project.total_reflections_pending == 0,
+        ErrorCode::RewardsOutstanding
+    );
+    Ok(())
+}
+
+// Sends a project-owned token account's whole balance to `destination`, then
+// closes it with the rent going to `rent_receiver`. Returns the amount swept.
+fn sweep_and_close_vault<'info>(
+    project: &Project,
+    project_info: &AccountInfo<'info>,
+    vault: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    rent_receiver: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &AccountInfo<'info>,
+) -> Result<u64> {
+    let seeds = project_signer_seeds!(project);
+    let signer = &[&seeds[..]];
+
+    // Native SOL vaults are closed with their lamports, no token balance to move
+    let amount = if is_native_sol(&mint.key()) {
+        0
+    } else {
+        read_token_account(vault, token_program.key)?.amount
+    };
+
+    if amount > 0 {
+        transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                TransferChecked {
+                    from: vault.clone(),
+                    to: destination.clone(),
+                    authority: project_info.clone(),
+                    mint: mint.to_account_info(),
+                },
+                signer,
+            ),
+            amount,
+            mint.decimals,
+        )?;
+    }
+
+    close_account(CpiContext::new_with_signer(
+        token_program.clone(),
+        CloseAccount {
+            account: vault.clone(),
+            destination: rent_receiver.clone(),
+            authority: project_info.clone(),
+        },
+        signer,
+    ))?;
+
+    Ok(amount)
+}
+
+// Converts a fixed-APY reward from staking-mint units to reward-mint units
+fn scale_to_reward_decimals(project: &Project, amount: u128) -> Result<u128> {
+    scale_decimals(amount, project.token_decimals, project.reward_decimals)
+}
+
+// Converts an amount from a mint with `from_decimals` to one with `to_decimals`,
+// rounding down
+fn scale_decimals(amount: u128, from_decimals: u8, to_decimals: u8) -> Result<u128> {
+    let scaled = if to_decimals >= from_decimals {
+        let factor = 10u128
+            .checked_pow((to_decimals - from_decimals) as u32)
+            .ok_or(ErrorCode::MathOverflow)?;
+        amount.checked_mul(factor).ok_or(ErrorCode::MathOverflow)?
+    } else {
+        let factor = 10u128
+            .checked_pow((from_decimals - to_decimals) as u32)
+            .ok_or(ErrorCode::MathOverflow)?;
+        amount.checked_div(factor).ok_or(ErrorCode::DivisionByZero)?
+    };
+    Ok(scaled)
+}
+
+// Splits remaining accounts into the fee part (referrer) and the trailing
+// reward streams, which must be all of the project's streams in index order
+fn split_remaining_accounts<'a, 'info>(
+    project: &Project,
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> Result<(&'a [AccountInfo<'info>], &'a [AccountInfo<'info>])> {
+    let stream_count = project.reward_stream_count as usize;
+    require!(
+        remaining_accounts.len() >= stream_count,
+        ErrorCode::MissingRewardStreams
+    );
+    Ok(remaining_accounts.split_at(remaining_accounts.len() - stream_count))
+}
+
+// Deserializes a reward stream passed as a remaining account
+fn load_reward_stream(info: &AccountInfo, project_key: &Pubkey, index: u8) -> Result<RewardStream> {
+    require!(
+        info.owner == &crate::ID && info.is_writable,
+        ErrorCode::InvalidRewardStream
+    );
+    let stream = RewardStream::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    require!(
+        stream.project == *project_key && stream.index == index,
+        ErrorCode::InvalidRewardStream
+    );
+    Ok(stream)
+}
+
+// Advances a stream's reward-per-token accumulator up to now, capped at its end time
+fn update_stream_pool(stream: &mut RewardStream, project: &Project) -> Result<()> {
+    let total_weight = project.total_weight;
+    let current_time = Clock::get()?.unix_timestamp.min(stream.end_time);
+    if current_time <= stream.last_update_time {
+        return Ok(());
+    }
+
+    // Nothing accrues before the pool starts
+    let elapsed = current_time
+        .saturating_sub(stream.last_update_time.max(project.pool_start_time))
+        .max(0) as u64;
+
+    // With nothing staked the interval's rewards stay unallocated in the vault
+    if total_weight > 0 && elapsed > 0 {
+
+        stream.reward_per_token_stored = stream.reward_per_token_stored
+            .checked_add(reward_per_token_delta(stream.reward_rate_per_second, elapsed, total_weight)?)
+            .ok_or(ErrorCode::MathOverflow)?;
+        stream.total_accrued = stream.total_accrued
+            .checked_add(
+                stream.reward_rate_per_second
+                    .checked_mul(elapsed)
+                    .ok_or(ErrorCode::MathOverflow)?,
+            )
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    stream.last_update_time = current_time;
+    Ok(())
+}
+
+// Credits a stake's share of a stream since its last checkpoint
+fn update_stream_reward(stream: &mut RewardStream, checkpoint: &mut StreamCheckpoint, weight: u64) -> Result<()> {
+    let earned = earned_since(weight, stream.reward_per_token_stored, checkpoint.reward_per_token_paid)?;
+
+    checkpoint.rewards_pending = checkpoint.rewards_pending
+        .checked_add(earned)
+        .ok_or(ErrorCode::MathOverflow)?;
+    stream.total_pending = stream.total_pending
+        .checked_add(earned)
+        .ok_or(ErrorCode::MathOverflow)?;
+    checkpoint.reward_per_token_paid = stream.reward_per_token_stored;
+    Ok(())
+}
+
+// Checkpoints every reward stream for `stake`. Must run before the stake amount
+// or the project total changes.
+fn settle_reward_streams(
+    project_key: &Pubkey,
+    project: &Project,
+    stake: &mut Stake,
+    stream_accounts: &[AccountInfo],
+) -> Result<()> {
+    require!(
+        stream_accounts.len() == project.reward_stream_count as usize,
+        ErrorCode::MissingRewardStreams
+    );
+
+    for (index, info) in stream_accounts.iter().enumerate() {
+        let mut stream = load_reward_stream(info, project_key, index as u8)?;
+        update_stream_pool(&mut stream, project)?;
+        update_stream_reward(&mut stream, &mut stake.stream_checkpoints[index], stake.weight)?;
+        stream.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+    }
+
+    Ok(())
+}
+
+// Settles every reward stream for `stake`, then drops its stream rewards and releases
+// them from each stream's accrued total so they can be swept
+fn forfeit_reward_streams(
+    project_key: &Pubkey,
+    project: &Project,
+    stake: &mut Stake,
+    stream_accounts: &[AccountInfo],
+) -> Result<()> {
+    require!(
+        stream_accounts.len() == project.reward_stream_count as usize,
+        ErrorCode::MissingRewardStreams
+    );
+
+    for (index, info) in stream_accounts.iter().enumerate() {
+        let mut stream = load_reward_stream(info, project_key, index as u8)?;
+        update_stream_pool(&mut stream, project)?;
+        let checkpoint = &mut stake.stream_checkpoints[index];
+        update_stream_reward(&mut stream, checkpoint, stake.weight)?;
+
+        let forfeited = checkpoint.rewards_pending;
+        checkpoint.rewards_pending = 0;
+        stream.total_pending = stream.total_pending.saturating_sub(forfeited);
+        stream.total_accrued = stream.total_accrued.saturating_sub(forfeited);
+        stream.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+    }
+
+    Ok(())
+}
+
+// Spreads a stream's unallocated funds over what is left of it
+fn respread_stream_rate(stream: &mut RewardStream, project: &Project, current_time: i64) -> Result<()> {
+    let unallocated = stream.total_funded
+        .checked_sub(stream.total_accrued)
+        .ok_or(ErrorCode::MathOverflow)?;
+    // Streams accrue only while the pool itself is running
+    let time_remaining = stream.end_time
+        .checked_sub(current_time.max(project.pool_start_time))
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(time_remaining > 0, ErrorCode::InvalidStreamEndTime);
+    stream.reward_rate_per_second = unallocated
+        .checked_div(time_remaining as u64)
+        .ok_or(ErrorCode::DivisionByZero)?;
+    Ok(())
+}
+
+// Fixed APY rate (1e9 scaled, per token per second) for a yearly rate in bps
+fn fixed_rate_per_second(rate_bps_per_year: u64) -> Result<u64> {
+    let numerator = (rate_bps_per_year as u128)
+        .checked_mul(1_000_000_000u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let denominator = (10_000u128)
+        .checked_mul(SECONDS_PER_YEAR as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let rate_per_second = numerator
+        .checked_div(denominator)
+        .ok_or(ErrorCode::DivisionByZero)?;
+
+    require!(rate_per_second <= u64::MAX as u128, ErrorCode::AprTooHigh);
+    Ok(rate_per_second as u64)
+}
+
+// A stake's contribution to Project.total_rate_weight
+fn rate_weight(weight: u64, rate: u64) -> u128 {
+    (weight as u128) * (rate as u128)
+}
+
+// Recomputes a stake's reward weight (amount × tier multiplier) and moves the
+// project's weight totals with it
+fn sync_stake_weight(project: &mut Project, stake: &mut Stake) -> Result<()> {
+    let multiplier_bps = project
+        .lock_tier_terms(stake.lock_tier)
+        .map_or(BASE_MULTIPLIER_BPS, |tier| tier.multiplier_bps);
+    let weight = (stake.amount as u128)
+        .checked_mul(multiplier_bps as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / BASE_MULTIPLIER_BPS as u128;
+    require!(weight <= u64::MAX as u128, ErrorCode::MathOverflow);
+    let weight = weight as u64;
+
+    project.total_weight = project.total_weight
+        .saturating_sub(stake.weight)
+        .checked_add(weight)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.total_rate_weight = project.total_rate_weight
+        .saturating_sub(rate_weight(stake.weight, stake.reward_rate_snapshot))
+        .checked_add(rate_weight(weight, stake.reward_rate_snapshot))
+        .ok_or(ErrorCode::MathOverflow)?;
+    stake.weight = weight;
+    project.projected_liability = projected_liability(project)?;
+
+    Ok(())
+}
+
+// Adds `amount` of principal to a stake and to the project totals
+fn add_to_stake(project: &mut Project, stake: &mut Stake, amount: u64) -> Result<()> {
+    stake.amount = stake.amount
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.total_staked = project.total_staked
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    sync_stake_weight(project, stake)
+}
+
+// Removes `amount` of principal from a stake, its lots and the project totals
+fn remove_from_stake(project: &mut Project, stake: &mut Stake, amount: u64, current_time: i64) -> Result<()> {
+    stake.amount = stake.amount
+        .checked_sub(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    consume_lots(stake, amount, current_time);
+
+    require!(
+        project.total_staked >= amount,
+        ErrorCode::InconsistentTotalStaked
+    );
+    project.total_staked = project.total_staked
+        .checked_sub(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    sync_stake_weight(project, stake)
+}
+
+// Drops a stake's pending rewards and reflections for emergency_withdraw. The stake
+// is settled on copies so everything it earned leaves total_rewards_accrued and can be
+// swept; if the reward math fails, only what was already pending is released and
+// the pool checkpoint is left for the next caller.
+fn forfeit_stake_rewards(project: &mut Project, stake: &mut Stake, current_time: i64) -> (u64, u64) {
+    let mut settled_project = Box::new(project.clone());
+    let mut settled = stake.clone();
+    let rewards_forfeited = match accrue_reward(&mut settled_project, &mut settled, current_time) {
+        Ok(()) => {
+            *project = *settled_project;
+            settled.rewards_pending
+        }
+        Err(_) => stake.rewards_pending,
+    };
+    // Reflections distributed to the stake but never credited are dropped with it
+    let reflections_forfeited = stake.reflections_pending
+        .saturating_add(unsettled_reflections(project, stake).unwrap_or(0));
+
+    project.total_rewards_accrued = project.total_rewards_accrued.saturating_sub(rewards_forfeited);
+    project.total_rewards_pending = project.total_rewards_pending.saturating_sub(rewards_forfeited);
+    project.total_reflections_pending = project.total_reflections_pending.saturating_sub(stake.reflections_pending);
+    project.total_reflections_unpaid = project.total_reflections_unpaid.saturating_sub(reflections_forfeited);
+
+    stake.rewards_pending = 0;
+    stake.reflections_pending = 0;
+    stake.reflection_debt = 0;
+    stake.acc_reward_per_token_paid = project.acc_reward_per_token;
+    stake.reflection_per_token_paid = project.reflection_per_token_stored;
+    stake.last_reward_update = current_time;
+
+    (rewards_forfeited, reflections_forfeited)
+}
+
+// Takes a stake's whole principal, staked and unbonding, off the project totals and
+// returns what the vault owes for it. Settle or forfeit the stake's rewards first.
+fn release_stake_principal(project: &mut Project, stake: &mut Stake, current_time: i64) -> Result<u64> {
+    let staked = stake.amount;
+    remove_from_stake(project, stake, staked, current_time)?;
+    let amount = staked
+        .checked_add(release_unbonding(project, stake))
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(amount)
+}
+
+// Clears a stake's unbonding amount and takes it off the project total
+fn release_unbonding(project: &mut Project, stake: &mut Stake) -> u64 {
+    let amount = stake.unbonding_amount;
+    stake.unbonding_amount = 0;
+    stake.unbonding_ready_time = 0;
+    project.total_unbonding = project.total_unbonding.saturating_sub(amount);
+    amount
+}
+
+// Moves unlocked principal into the stake's unbonding amount, where it stops earning.
+// Call after update_reward and settle_reward_streams. Returns the new ready time.
+fn queue_unbonding(project: &mut Project, stake: &mut Stake, amount: u64, current_time: i64) -> Result<i64> {
+    require_unlocked(project, stake, amount, current_time)?;
+    remove_from_stake(project, stake, amount, current_time)?;
+
+    let ready_time = current_time
+        .checked_add(project.unbonding_seconds as i64)
+        .ok_or(ErrorCode::MathOverflow)?;
+    stake.unbonding_amount = stake.unbonding_amount
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    stake.unbonding_ready_time = ready_time;
+    project.total_unbonding = project.total_unbonding
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(ready_time)
+}
+
+// Releases the unbonding amount once its cooldown has passed; returns what to pay out
+fn take_unbonded(project: &mut Project, stake: &mut Stake, current_time: i64) -> Result<u64> {
+    require!(stake.unbonding_amount > 0, ErrorCode::NothingUnbonding);
+    require!(current_time >= stake.unbonding_ready_time, ErrorCode::UnbondingNotReady);
+    Ok(release_unbonding(project, stake))
+}
+
+// Puts the unbonding amount back into the stake, unlocked since it already served its
+// lock. Call after update_reward and settle_reward_streams. Returns the amount restaked.
+fn restake_unbonding(project: &mut Project, stake: &mut Stake, current_time: i64) -> Result<u64> {
+    require!(stake.unbonding_amount > 0, ErrorCode::NothingUnbonding);
+    let amount = release_unbonding(project, stake);
+    add_to_stake(project, stake, amount)?;
+    lock_deposit(project, stake, amount, current_time)?;
+    require_rewards_funded(project)?;
+    Ok(amount)
+}
+
+// Opens the legacy migration. Accrual restarts now under the current model with the
+// whole legacy total_staked registered at 1x and the current rate, so stakes that
+// have not migrated yet keep their share and nobody collects theirs meanwhile.
+fn begin_legacy_migration(project: &mut Project, current_time: i64) -> Result<()> {
+    project.legacy_cutoff_time = current_time;
+    project.legacy_total_staked = project.total_staked;
+    project.legacy_reward_rate = project.reward_rate_per_second;
+
+    project.last_update_time = current_time;
+    project.acc_reward_per_token = 0;
+    project.total_weight = project.total_staked;
+    project.total_rate_weight = if project.rate_mode == 0 {
+        rate_weight(project.total_staked, project.reward_rate_per_second)
+    } else {
+        0
+    };
+
+    // Rewards already paid out were accrued; pending ones are added by migrate_stake
+    project.total_rewards_accrued = project.total_rewards_accrued.max(project.total_rewards_claimed);
+    project.projected_liability = projected_liability(project)?;
+    Ok(())
+}
+
+// Rewards a legacy stake earned under the old formula from `credited_until` to the
+// migration cutoff: amount × rate × time over 1e9 for fixed APY, or over the total
+// staked at the cutoff for variable pools
+fn legacy_accrual(project: &Project, stake: &Stake, credited_until: i64) -> Result<u64> {
+    require!(project.legacy_cutoff_time > 0, ErrorCode::ProjectNotMigrated);
+    require!(
+        credited_until >= stake.last_stake_timestamp && credited_until <= project.legacy_cutoff_time,
+        ErrorCode::InvalidLegacyCredit
+    );
+
+    let end = project.legacy_cutoff_time.min(project.pool_end_time);
+    let elapsed = end.saturating_sub(credited_until).max(0) as u64;
+    if elapsed == 0 || stake.amount == 0 {
+        return Ok(0);
+    }
+
+    let intermediate = (project.legacy_reward_rate as u128)
+        .checked_mul(elapsed as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_mul(stake.amount as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let divisor = if project.rate_mode == 0 {
+        1_000_000_000u128
+    } else {
+        project.legacy_total_staked as u128
+    };
+    let rewards = intermediate
+        .checked_div(divisor)
+        .ok_or(ErrorCode::DivisionByZero)?;
+
+    require!(rewards <= u64::MAX as u128, ErrorCode::MathOverflow);
+    Ok(rewards as u64)
+}
+
+// Moves a legacy stake onto the current model: credits its legacy accrual, registers
+// its pending rewards and reflections with the project and starts its checkpoints at
+// the cutoff. Its weight was registered by begin_legacy_migration. Returns the credit.
+fn register_legacy_stake(project: &mut Project, stake: &mut Stake, credited_until: i64) -> Result<u64> {
+    let legacy_rewards = legacy_accrual(project, stake, credited_until)?;
+    stake.rewards_pending = stake.rewards_pending
+        .checked_add(legacy_rewards)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    project.total_rewards_accrued = project.total_rewards_accrued
+        .checked_add(stake.rewards_pending)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.total_rewards_pending = project.total_rewards_pending
+        .checked_add(stake.rewards_pending)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.total_reflections_pending = project.total_reflections_pending
+        .checked_add(stake.reflections_pending)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    stake.weight = stake.amount;
+    stake.reward_rate_snapshot = project.legacy_reward_rate;
+    stake.acc_reward_per_token_paid = 0;
+    stake.last_reward_update = project.legacy_cutoff_time;
+    stake.stream_checkpoints = [StreamCheckpoint::default(); MAX_REWARD_STREAMS];
+
+    project.projected_liability = projected_liability(project)?;
+    Ok(legacy_rewards)
+}
+
+// Tops up rent and resizes a legacy-layout account; new trailing bytes are zero-filled
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    target_len: usize,
+) -> Result<()> {
+    let rent_required = Rent::get()?.minimum_balance(target_len);
+    let shortfall = rent_required.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    account.resize(target_len)?;
+    Ok(())
+}
+
+// Moves a fixed-APY stake onto the pool's current rate. Call after update_reward
+// so time already elapsed is paid at the old rate. Returns the old rate if it changed.
+fn reprice_stake(project: &mut Project, stake: &mut Stake) -> Result<Option<u64>> {
+    let old_rate = stake.reward_rate_snapshot;
+    if project.rate_mode != 0 || old_rate == project.reward_rate_per_second {
+        return Ok(None);
+    }
+
+    project.total_rate_weight = project.total_rate_weight
+        .saturating_sub(rate_weight(stake.weight, old_rate))
+        .checked_add(rate_weight(stake.weight, project.reward_rate_per_second))
+        .ok_or(ErrorCode::MathOverflow)?;
+    stake.reward_rate_snapshot = project.reward_rate_per_second;
+    project.projected_liability = projected_liability(project)?;
+
+    Ok(Some(old_rate))
+}
+
+// Rewards the pool is committed to: everything accrued so far plus what is still
+// scheduled from the last checkpoint to pool_end_time - what current fixed-APY stakes
+// earn, or the variable pool rate over the remaining window.
+// Unaffected by time passing or claims, so it only moves when stakes or rates change.
+fn projected_liability(project: &Project) -> Result<u64> {
+    let mut liability = project.total_rewards_accrued as u128;
+
+    let remaining = accrual_window(project, project.last_update_time, project.pool_end_time) as u128;
+    let future = if project.rate_mode == 0 {
+        scale_to_reward_decimals(
+            project,
+            project.total_rate_weight
+                .checked_mul(remaining)
+                .ok_or(ErrorCode::MathOverflow)?,
+        )?
+        .checked_div(1_000_000_000u128)
+        .ok_or(ErrorCode::DivisionByZero)?
+    } else {
+        (project.reward_rate_per_second as u128)
+            .checked_mul(remaining)
+            .ok_or(ErrorCode::MathOverflow)?
+    };
+    liability = liability
+        .checked_add(future)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    require!(liability <= u64::MAX as u128, ErrorCode::MathOverflow);
+    Ok(liability as u64)
+}
+
+// Checkpoints the pool and moves its end time. Ended pools cannot be reopened:
+// fixed-APY stakes would then be paid for the idle gap the pool never accrued.
+fn reschedule_pool_end(project: &mut Project, new_end_time: i64, current_time: i64) -> Result<()> {
+    require!(current_time < project.pool_end_time, ErrorCode::PoolEnded);
+    require!(
+        new_end_time > current_time.max(project.pool_start_time),
+        ErrorCode::InvalidPoolEndTime
+    );
+
+    accrue_pool_reward(project, current_time)?;
+
+    project.pool_end_time = new_end_time;
+    project.pool_duration_seconds = new_end_time
+        .checked_sub(project.pool_start_time)
+        .ok_or(ErrorCode::MathOverflow)? as u64;
+
+    if project.rate_mode == 1 {
+        // Everything not yet accrued is spread over what is left of the new window
+        let unallocated = project.total_rewards_deposited
+            .saturating_sub(project.total_rewards_accrued);
+        let time_remaining = accrual_window(project, current_time, new_end_time);
+        project.reward_rate_per_second = unallocated
+            .checked_div(time_remaining)
+            .ok_or(ErrorCode::DivisionByZero)?;
+    }
+
+    project.projected_liability = projected_liability(project)?;
+    require_rewards_funded(project)
+}
+
+// Records rewards that reached the reward vault. The pool is checkpointed at the old
+// rate first; variable pools then spread everything not yet accrued over what is left.
+fn credit_reward_deposit(project: &mut Project, amount: u64, current_time: i64) -> Result<()> {
+    accrue_pool_reward(project, current_time)?;
+
+    project.total_rewards_deposited = project.total_rewards_deposited
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    if project.rate_mode == 1 {
+        let time_remaining = accrual_window(project, current_time, project.pool_end_time);
+        if time_remaining > 0 {
+            let unallocated = project.total_rewards_deposited
+                .saturating_sub(project.total_rewards_accrued);
+            project.reward_rate_per_second = unallocated
+                .checked_div(time_remaining)
+                .ok_or(ErrorCode::DivisionByZero)?;
+        }
+    }
+
+    project.projected_liability = projected_liability(project)?;
+    Ok(())
+}
+
+// Fixed-APY pools may not promise more than has been deposited into the reward vault
+fn require_rewards_funded(project: &Project) -> Result<()> {
+    if project.rate_mode == 0 {
+        require!(
+            project.projected_liability <= project.total_rewards_deposited,
+            ErrorCode::InsufficientRewardFunding
+        );
+    }
+    Ok(())
+}
+
+// Turns a stake's pending `rewards` into principal; `received` is what the staking vault
+// got for them. With compound_resets_lock the whole stake re-locks at its tier and moves
+// onto the current fixed rate, otherwise the compounded amount joins its latest lock.
+// Returns the old rate if the stake was repriced.
+fn compound_rewards(
+    project: &mut Project,
+    stake: &mut Stake,
+    rewards: u64,
+    received: u64,
+    current_time: i64,
+) -> Result<Option<u64>> {
+    stake.rewards_pending = 0;
+    stake.total_rewards_claimed = stake.total_rewards_claimed
+        .checked_add(rewards)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let resets_lock = project.compound_resets_lock;
+    let mut old_rate = None;
+    if resets_lock {
+        stake.last_stake_timestamp = current_time;
+        // Re-locking moves the position onto the current fixed rate
+        old_rate = reprice_stake(project, stake)?;
+    }
+    add_to_stake(project, stake, received)?;
+    let unlock_time = if resets_lock {
+        current_time
+            .checked_add(project.tier_lockup_seconds(stake.lock_tier) as i64)
+            .ok_or(ErrorCode::MathOverflow)?
+    } else {
+        latest_unlock_time(stake).max(current_time)
+    };
+    lock_deposit(project, stake, received, unlock_time)?;
+
+    project.total_rewards_claimed = project.total_rewards_claimed
+        .checked_add(rewards)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.total_rewards_pending = project.total_rewards_pending.saturating_sub(rewards);
+
+    Ok(old_rate)
+}
+
+// Staking-token amount a fixed-APY pool can still take at its current rate and 1x weight
+fn fixed_pool_capacity(project: &Project, current_time: i64) -> Result<u64> {
+    if project.rate_mode != 0 {
+        return Ok(u64::MAX);
+    }
+
+    let remaining = accrual_window(project, current_time.max(project.last_update_time), project.pool_end_time);
+    if remaining == 0 {
+        return Ok(0);
+    }
+    if project.reward_rate_per_second == 0 {
+        return Ok(u64::MAX);
+    }
+
+    let headroom = project.total_rewards_deposited
+        .saturating_sub(projected_liability(project)?) as u128;
+
+    // Convert the reward-mint headroom back to staking-mint units
+    let headroom = scale_decimals(headroom, project.reward_decimals, project.token_decimals)?;
+
+    let cost_per_token = (project.reward_rate_per_second as u128)
+        .checked_mul(remaining as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let capacity = headroom
+        .checked_mul(1_000_000_000u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(cost_per_token)
+        .ok_or(ErrorCode::DivisionByZero)?;
+
+    Ok(capacity.min(u64::MAX as u128) as u64)
+}
+
+// Pays a stake's whole principal, including anything unbonding, from the staking
+// vault to its withdrawal wallet
+fn return_stake_principal<'info>(
+    project: &mut Account<'info, Project>,
+    stake: &mut Account<'info, Stake>,
+    destination: &AccountInfo<'info>,
+    staking_vault: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<u64> {
+    validate_withdrawal_account(destination, token_program.key, &mint.key(), stake)?;
+
+    update_reward(project, stake)?;
+    let amount = release_stake_principal(project, stake, Clock::get()?.unix_timestamp)?;
+
+    let seeds = project_signer_seeds!(project);
+    let signer = &[&seeds[..]];
+
+    transfer_tokens(
+        staking_vault.clone(),
+        destination.clone(),
+        project.to_account_info(),
+        mint,
+        token_program.clone(),
+        system_program.clone(),
+        amount,
+        Some(signer),
+    )?;
+
+    emit!(EmergencyStakeReturned {
+        project: project.key(),
+        user: stake.user,
+        destination: destination.key(),
+        amount,
+    });
+
+    Ok(amount)
+}
+
+// Accumulator increase when `rate` per second is shared by `total_weight` for `elapsed` seconds
+fn reward_per_token_delta(rate: u64, elapsed: u64, total_weight: u64) -> Result<u128> {
+    let delta = (rate as u128)
+        .checked_mul(elapsed as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_mul(REWARD_PRECISION)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(total_weight as u128)
+        .ok_or(ErrorCode::DivisionByZero)?;
+    Ok(delta)
+}
+
+// Rewards earned by `weight` between the `paid` checkpoint and the accumulator value `acc`
+fn earned_since(weight: u64, acc: u128, paid: u128) -> Result<u64> {
+    let earned = (weight as u128)
+        .checked_mul(acc.checked_sub(paid).ok_or(ErrorCode::MathOverflow)?)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(REWARD_PRECISION)
+        .ok_or(ErrorCode::DivisionByZero)?;
+    require!(earned <= u64::MAX as u128, ErrorCode::MathOverflow);
+    Ok(earned as u64)
+}
+
+// Advances the project-wide reward checkpoint and the accrued-rewards total to now
+fn update_pool_reward(project: &mut Project) -> Result<()> {
+    accrue_pool_reward(project, Clock::get()?.unix_timestamp)
+}
+
+// Seconds of [from, to] that fall inside [pool_start_time, pool_end_time]
+fn accrual_window(project: &Project, from: i64, to: i64) -> u64 {
+    let start = from.max(project.pool_start_time);
+    let end = to.min(project.pool_end_time);
+    end.saturating_sub(start).max(0) as u64
+}
+
+fn accrue_pool_reward(project: &mut Project, current_time: i64) -> Result<()> {
+    if project.total_weight > 0 && current_time > project.last_update_time {
+        let effective_time = accrual_window(project, project.last_update_time, current_time);
+        
+        if effective_time > 0 {
+            let intermediate = (project.reward_rate_per_second as u128)
+                .checked_mul(effective_time as u128)
+                .ok_or(ErrorCode::MathOverflow)?;
+
+            // Rewards owed to all stakers over this interval; fixed-APY stakes
+            // each earn at their own snapshot rate
+            let accrued_u128 = if project.rate_mode == 0 {
+                scale_to_reward_decimals(
+                    project,
+                    project.total_rate_weight
+                        .checked_mul(effective_time as u128)
+                        .ok_or(ErrorCode::MathOverflow)?,
+                )?
+                .checked_div(1_000_000_000u128)
+                .ok_or(ErrorCode::DivisionByZero)?
+            } else {
+                // Variable pools split the pool rate pro rata through the accumulator
+                project.acc_reward_per_token = project.acc_reward_per_token
+                    .checked_add(reward_per_token_delta(
+                        project.reward_rate_per_second,
+                        effective_time,
+                        project.total_weight,
+                    )?)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                intermediate
+            };
+            require!(accrued_u128 <= u64::MAX as u128, ErrorCode::MathOverflow);
+
+            project.total_rewards_accrued = project.total_rewards_accrued
+                .checked_add(accrued_u128 as u64)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+    }
+    
+    project.last_update_time = current_time;
+
+    Ok(())
+}
+
+fn update_reward(project: &mut Project, stake: &mut Stake) -> Result<()> {
+    accrue_reward(project, stake, Clock::get()?.unix_timestamp)
+}
+
+fn accrue_reward(project: &mut Project, stake: &mut Stake, current_time: i64) -> Result<()> {
+    accrue_pool_reward(project, current_time)?;
+
+    // This stake's share of the accumulator since its checkpoint: variable-pool
+    // rewards, plus redistributed early exit penalties in either mode
+    let earned = earned_since(
+        stake.weight,
+        project.acc_reward_per_token,
+        stake.acc_reward_per_token_paid,
+    )?;
+    stake.rewards_pending = stake.rewards_pending
+        .checked_add(earned)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.total_rewards_pending = project.total_rewards_pending
+        .checked_add(earned)
+        .ok_or(ErrorCode::MathOverflow)?;
+    stake.acc_reward_per_token_paid = project.acc_reward_per_token;
+
+    if project.rate_mode == 1 {
+        stake.last_reward_update = current_time;
+        return Ok(());
+    }
+    
+    // Fixed APY: weight × snapshot rate × time for this specific stake
+    if stake.weight > 0 && project.total_weight > 0 {
+        let effective_time = accrual_window(project, stake.last_reward_update, current_time);
+        
+        if effective_time > 0 {
+            let intermediate = (stake.reward_rate_snapshot as u128)
+                .checked_mul(effective_time as u128)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_mul(stake.weight as u128)
+                .ok_or(ErrorCode::MathOverflow)?;
+            
+            // Fixed APY: reward_rate_per_second has 1e9 scaling
+            // Formula: (weight × rate × time) / 1e9, converted to reward-mint decimals
+            let new_rewards_u128 = scale_to_reward_decimals(project, intermediate)?
+                .checked_div(1_000_000_000u128)
+                .ok_or(ErrorCode::DivisionByZero)?;
+            
+            require!(new_rewards_u128 <= u64::MAX as u128, ErrorCode::MathOverflow);
+            let new_rewards = new_rewards_u128 as u64;
+            
+            stake.rewards_pending = stake.rewards_pending
+                .checked_add(new_rewards)
+                .ok_or(ErrorCode::MathOverflow)?;
+            project.total_rewards_pending = project.total_rewards_pending
+                .checked_add(new_rewards)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+    }
+    
+    stake.last_reward_update = current_time;
+    
+    Ok(())
+}
+
+fn update_reflection_internal(
+    project: &mut Account<Project>,
+    stake: &mut Account<Stake>,
+    reflection_vault: Option<&AccountInfo>,
+    update_user_paid_marker: bool,
+) -> Result<()> {
+    if !project.enable_reflections {
+        return Ok(());
+    }
+    
+    if project.reflection_token.is_none() {
+        return Ok(());
+    }
+    
+    let vault = reflection_vault.ok_or(ErrorCode::ReflectionVaultRequired)?;
+    let current_time = Clock::get()?.unix_timestamp;
+    
+    // Determine reflection type
+    let reflection_token_mint = project.reflection_token.unwrap();
+    let native_sol = Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+    let is_native_sol = reflection_token_mint == native_sol;
+    
+    // ✅ Calculate distributable balance based on reflection type
+    let current_balance = if is_native_sol {
+        // ✅ Native SOL - read from Project PDA lamports with 0.003 SOL buffer
+        let total_lamports = vault.lamports();
+        let rent = Rent::get()?;
+        let rent_exempt_minimum = rent.minimum_balance(vault.data_len());
+        let fixed_buffer = rent_exempt_minimum.saturating_add(3_000_000);  // ✅ 0.003 SOL buffer
+        
+        msg!("🔍 Native SOL reflection calculation:");
+        msg!("   Total lamports: {}", total_lamports);
+        msg!("   Rent exempt minimum: {}", rent_exempt_minimum);
+        msg!("   Fixed buffer (rent + 0.003 SOL): {}", fixed_buffer);
+        msg!("   Distributable balance: {}", total_lamports.saturating_sub(fixed_buffer));
+        
+        total_lamports.saturating_sub(fixed_buffer)
+    } else {
+        // ✅ SPL/Token-2022 - read from Project PDA's standard ATA
+        // (Works for both self-reflection and external reflection)
+        let vault_data = vault.try_borrow_data()?;
+        if vault_data.len() < 72 {
+            return Err(ErrorCode::InvalidReflectionVault.into());
+        }
+        u64::from_le_bytes(vault_data[64..72].try_into().unwrap())
+    };
+    
+    // If balance decreased (e.g., admin withdrew reflections), reset baseline
+    if current_balance < project.last_reflection_balance {
+        project.last_reflection_balance = current_balance;
+        project.last_reflection_update_time = current_time;
+        return Ok(());
+    }
+    
+    distribute_reflections(project, current_balance, current_time)?;
+    settle_reflections(project, stake, update_user_paid_marker)
+}
+
+// Spreads reflections that reached the vault since the last update over the current
+// stake. With nothing staked they wait in the vault for the next staker.
+fn distribute_reflections(project: &mut Project, current_balance: u64, current_time: i64) -> Result<()> {
+    let new_tokens = current_balance.saturating_sub(project.last_reflection_balance);
+    if new_tokens == 0 || project.total_staked == 0 {
+        return Ok(());
+    }
+
+    let per_token_u128 = (new_tokens as u128)
+        .checked_mul(1_000_000_000u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(project.total_staked as u128)
+        .ok_or(ErrorCode::DivisionByZero)?;
+    
+    require!(per_token_u128 <= u64::MAX as u128, ErrorCode::MathOverflow);
+    let per_token_rate = per_token_u128 as u64;
+    
+    project.reflection_per_token_stored = project.reflection_per_token_stored
+        .checked_add(per_token_rate)
+        .ok_or(ErrorCode::MathOverflow)?;
+    project.total_reflections_unpaid = project.total_reflections_unpaid
+        .checked_add(new_tokens)
+        .ok_or(ErrorCode::MathOverflow)?;
+    
+    project.last_reflection_balance = current_balance;
+    project.last_reflection_update_time = current_time;
+    
+    msg!("✅ Reflection update:");
+    msg!("   New reflections: {}", new_tokens);
+    msg!("   Per token rate: {}", per_token_rate);
+    msg!("   Total per token stored: {}", project.reflection_per_token_stored);
+
+    Ok(())
+}
+
+// Reflection-vault balance no staker has a claim on. Reflections the next refresh
+// would distribute and everything distributed but not yet paid out stay in the vault.
+fn reflection_surplus(project: &Project, balance: u64) -> u64 {
+    let owed = project.total_reflections_unpaid.max(project.total_reflections_pending);
+    balance
+        .min(project.last_reflection_balance)
+        .saturating_sub(owed)
+}
+
+// Reflections distributed to a stake since its checkpoint and not yet credited
+fn unsettled_reflections(project: &Project, stake: &Stake) -> Result<u64> {
+    let rate_diff = project.reflection_per_token_stored
+        .saturating_sub(stake.reflection_per_token_paid);
+    let earned_u128 = (rate_diff as u128)
+        .checked_mul(stake.amount as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(1_000_000_000u128)
+        .ok_or(ErrorCode::DivisionByZero)?;
+    
+    require!(earned_u128 <= u64::MAX as u128, ErrorCode::MathOverflow);
+    Ok(earned_u128 as u64)
+}
+
+// Credits a stake's share of distributed reflections to its pending reflections
+fn settle_reflections(project: &mut Project, stake: &mut Stake, update_user_paid_marker: bool) -> Result<()> {
+    if stake.amount == 0 {
+        return Ok(());
+    }
+
+    let earned = unsettled_reflections(project, stake)?;
+    if earned > 0 {
+        let net_earned = earned.saturating_sub(stake.reflection_debt);
+        
+        stake.reflections_pending = stake.reflections_pending
+            .checked_add(net_earned)
+            .ok_or(ErrorCode::MathOverflow)?;
+        project.total_reflections_pending = project.total_reflections_pending
+            .checked_add(net_earned)
+            .ok_or(ErrorCode::MathOverflow)?;
+        
+        stake.reflection_debt = 0;
+        
+        msg!("   User earned: {}", net_earned);
+        msg!("   Total pending: {}", stake.reflections_pending);
+    }
+    
+    if update_user_paid_marker {
+        stake.reflection_per_token_paid = project.reflection_per_token_stored;
+    }
+    
+    Ok(())
+}
+
+fn update_reflection(
+    project: &mut Account<Project>,
+    stake: &mut Account<Stake>,
+    reflection_vault: Option<&AccountInfo>,
+) -> Result<()> {
+    update_reflection_internal(project, stake, reflection_vault, true)
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Platform::INIT_SPACE,
+        seeds = [b"platform_v2"],
+        bump
+    )]
+    pub platform: Account<'info, Platform>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    /// CHECK: Fee collector wallet
+    pub fee_collector: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct CreateProject<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Project::INIT_SPACE,
+        seeds = [b"project", token_mint.key().as_ref(), &pool_id.to_le_bytes()],
+        bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+    
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = project,
+        token::token_program = token_program,
+    )]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"reward_vault", project.key().as_ref()],
+        bump,
+        token::mint = reward_mint,
+        token::authority = project,
+        token::token_program = reward_token_program,
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    
+    /// Mint rewards are paid in - pass token_mint for a single-mint pool
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64, params: InitializePoolParams)]
+pub struct InitializePool<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Box<Account<'info, Project>>,
+    
+    #[account(
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+    )]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    /// CHECK: Optional reflection token mint - validated in instruction logic
+    pub reflection_token_mint: Option<AccountInfo<'info>>,
+    
+    /// CHECK: Reflection ATA - manually validated/created
+    #[account(mut)]
+    pub reflection_token_account: Option<AccountInfo<'info>>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    /// CHECK: Only used if reflections enabled
+    pub associated_token_program: Option<AccountInfo<'info>>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reflection_token_program: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct Deposit<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+    
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Stake::INIT_SPACE,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub stake: Account<'info, Stake>,
+    
+    /// CHECK: Project staking vault - owner, mint and authority checked in validate()
+    #[account(
+        mut,
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+        constraint = staking_vault.key() == project.staking_vault @ ErrorCode::InvalidStakingVault
+    )]
+    pub staking_vault: AccountInfo<'info>,
+    
+    /// CHECK: Can be wallet (Native SOL) or TokenAccount (SPL) - checked in validate()
+    #[account(mut)]
+    pub user_token_account: AccountInfo<'info>,
+        
+    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL) - checked in validate()
+    #[account(mut)]
+    pub fee_collector_token_account: AccountInfo<'info>,
+        
+    /// CHECK: Fee collector wallet
+    #[account(
+        mut,
+        constraint = fee_collector.key() == platform.fee_collector @ ErrorCode::InvalidFeeCollector
+    )]
+    pub fee_collector: AccountInfo<'info>,
+        
+    /// CHECK: Optional reflection vault - checked in validate()
+    pub reflection_vault: Option<AccountInfo<'info>>,    
+    #[account(
+        constraint = token_mint_account.key() == project.token_mint @ ErrorCode::WrongTokenType
+    )]
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct Withdraw<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+    
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+    
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+    
+    #[account(
+        mut,
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+        constraint = staking_vault.mint == token_mint @ ErrorCode::WrongTokenType,
+        constraint = staking_vault.key() == project.staking_vault @ ErrorCode::UnauthorizedVault
+    )]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    /// CHECK: Can be wallet (Native SOL) or TokenAccount (SPL/Token-2022) - checked in validate()
+    #[account(mut)]
+    pub withdrawal_token_account: AccountInfo<'info>,
+
+    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL) - checked in validate()
+    #[account(mut)]
+    pub fee_collector_token_account: AccountInfo<'info>,
+        
+    /// CHECK: Fee collector wallet
+    #[account(
+        mut,
+        constraint = fee_collector.key() == platform.fee_collector @ ErrorCode::InvalidFeeCollector
+    )]
+    pub fee_collector: AccountInfo<'info>,
+    
+    /// CHECK: Optional reflection vault - checked in validate()
+    pub reflection_vault: Option<AccountInfo<'info>>,
+
+    /// CHECK: Project reward vault, needed when an early exit penalty goes to the
+    /// reward vault or to stakers - checked in validate()
+    #[account(mut)]
+    pub reward_vault: Option<AccountInfo<'info>>,
+    
+    #[account(
+        mut,
+        constraint = token_mint_account.key() == project.token_mint @ ErrorCode::WrongTokenType
+    )]
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct Claim<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+    
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+    
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+    
+    /// CHECK: Project reward vault - owner, mint and authority checked in validate()
+    #[account(
+        mut,
+        seeds = [b"reward_vault", project.key().as_ref()],
+        bump,
+        constraint = reward_vault.key() == project.reward_vault @ ErrorCode::InvalidRewardVault
+    )]
+    pub reward_vault: AccountInfo<'info>,
+        
+    /// CHECK: Can be wallet (Native SOL) or TokenAccount (SPL) - checked in validate()
+    #[account(mut)]
+    pub user_token_account: AccountInfo<'info>,
+    
+    /// CHECK: Fee collector wallet
+    #[account(
+        mut,
+        constraint = fee_collector.key() == platform.fee_collector @ ErrorCode::InvalidFeeCollector
+    )]
+    pub fee_collector: AccountInfo<'info>,
+    
+    /// CHECK: Optional reflection vault - checked in validate()
+    pub reflection_vault: Option<AccountInfo<'info>>,    
+    /// Reward mint - the staking mint unless the project has a separate reward_mint
+    #[account(
+        constraint = token_mint_account.key() == project.reward_mint_key() @ ErrorCode::WrongTokenType
+    )]
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Deposit<'info> {
+    /// Ties the unchecked vault, user and fee accounts to the project and platform
+    pub fn validate(&self) -> Result<()> {
+        let token_program = self.token_program.key();
+        let mint = self.token_mint_account.key();
+
+        validate_token_account(&self.staking_vault, &token_program, &mint, &self.project.key())?;
+        validate_wallet_account(&self.user_token_account, &token_program, &mint, &self.user.key())?;
+        validate_wallet_account(
+            &self.fee_collector_token_account,
+            &token_program,
+            &mint,
+            &self.platform.fee_collector,
+        )?;
+        validate_reflection_vault(&self.project, self.reflection_vault.as_ref())
+    }
+}
+
+impl<'info> Withdraw<'info> {
+    /// Ties the unchecked destination and fee accounts to the withdrawal wallet and platform
+    pub fn validate(&self) -> Result<()> {
+        let token_program = self.token_program.key();
+        let mint = self.token_mint_account.key();
+
+        require!(
+            self.staking_vault.to_account_info().owner == &token_program,
+            ErrorCode::InvalidTokenProgram
+        );
+        validate_withdrawal_account(&self.withdrawal_token_account, &token_program, &mint, &self.stake)?;
+        validate_wallet_account(
+            &self.fee_collector_token_account,
+            &token_program,
+            &mint,
+            &self.platform.fee_collector,
+        )?;
+        if let Some(reward_vault) = self.reward_vault.as_ref() {
+            require!(reward_vault.key() == self.project.reward_vault, ErrorCode::UnauthorizedVault);
+        }
+        validate_reflection_vault(&self.project, self.reflection_vault.as_ref())
+    }
+}
+
+impl<'info> RequestUnstake<'info> {
+    pub fn validate(&self) -> Result<()> {
+        validate_reflection_vault(&self.project, self.reflection_vault.as_ref())
+    }
+}
+
+impl<'info> CompleteUnstake<'info> {
+    /// Ties the unchecked destination and fee accounts to the withdrawal wallet and platform
+    pub fn validate(&self) -> Result<()> {
+        let token_program = self.token_program.key();
+        let mint = self.token_mint_account.key();
+
+        require!(
+            self.staking_vault.to_account_info().owner == &token_program,
+            ErrorCode::InvalidTokenProgram
+        );
+        validate_withdrawal_account(&self.withdrawal_token_account, &token_program, &mint, &self.stake)?;
+        validate_wallet_account(
+            &self.fee_collector_token_account,
+            &token_program,
+            &mint,
+            &self.platform.fee_collector,
+        )
+    }
+}
+
+impl<'info> Claim<'info> {
+    /// Ties the unchecked reward vault and payout account to the project and withdrawal wallet
+    pub fn validate(&self) -> Result<()> {
+        let token_program = self.token_program.key();
+        let mint = self.token_mint_account.key();
+
+        validate_token_account(&self.reward_vault, &token_program, &mint, &self.project.key())?;
+        validate_withdrawal_account(&self.user_token_account, &token_program, &mint, &self.stake)?;
+        validate_reflection_vault(&self.project, self.reflection_vault.as_ref())
+    }
+}
+
+impl<'info> ClaimReflections<'info> {
+    /// Ties the reflection vault and payout account to the project and withdrawal wallet
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            Some(self.reflection_token_mint.key()) == self.project.reflection_token,
+            ErrorCode::WrongTokenType
+        );
+        validate_reflection_vault(&self.project, Some(&self.reflection_vault))?;
+        validate_withdrawal_account(
+            &self.user_reflection_account,
+            &self.token_program.key(),
+            &self.reflection_token_mint.key(),
+            &self.stake,
+        )
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct ClaimReflections<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+    
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+
+    /// CHECK: For Native SOL = Project PDA, For SPL = Project PDA's standard ATA
+    #[account(mut)]
+    pub reflection_vault: AccountInfo<'info>,
+    
+    /// CHECK: User's wallet (for Native SOL) or user's ATA (for SPL)
+    #[account(mut)]
+    pub user_reflection_account: AccountInfo<'info>,
+    
+    pub reflection_token_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct Exit<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Box<Account<'info, Stake>>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+        constraint = staking_vault.mint == token_mint @ ErrorCode::WrongTokenType,
+        constraint = staking_vault.key() == project.staking_vault @ ErrorCode::UnauthorizedVault
+    )]
+    pub staking_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Project reward vault - owner, mint and authority checked in validate()
+    #[account(
+        mut,
+        seeds = [b"reward_vault", project.key().as_ref()],
+        bump,
+        constraint = reward_vault.key() == project.reward_vault @ ErrorCode::InvalidRewardVault
+    )]
+    pub reward_vault: AccountInfo<'info>,
+
+    /// CHECK: Receives principal - checked in validate()
+    #[account(mut)]
+    pub withdrawal_token_account: AccountInfo<'info>,
+
+    /// CHECK: Receives rewards - same as withdrawal_token_account for single-mint pools, checked in validate()
+    #[account(mut)]
+    pub reward_token_account: AccountInfo<'info>,
+
+    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL) - checked in validate()
+    #[account(mut)]
+    pub fee_collector_token_account: AccountInfo<'info>,
+
+    /// CHECK: Fee collector wallet
+    #[account(
+        mut,
+        constraint = fee_collector.key() == platform.fee_collector @ ErrorCode::InvalidFeeCollector
+    )]
+    pub fee_collector: AccountInfo<'info>,
+
+    /// CHECK: Optional reflection vault - checked in validate()
+    #[account(mut)]
+    pub reflection_vault: Option<AccountInfo<'info>>,
+
+    /// CHECK: Optional reflection payout account - checked against the withdrawal wallet in exit
+    #[account(mut)]
+    pub user_reflection_account: Option<AccountInfo<'info>>,
+
+    pub reflection_token_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    pub reflection_token_program: Option<Interface<'info, TokenInterface>>,
+
+    #[account(
+        constraint = token_mint_account.key() == project.token_mint @ ErrorCode::WrongTokenType
+    )]
+    pub token_mint_account: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        constraint = reward_mint_account.key() == project.reward_mint_key() @ ErrorCode::WrongTokenType
+    )]
+    pub reward_mint_account: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Exit<'info> {
+    /// Ties the vaults, payout and fee accounts to the project, withdrawal wallet and platform
+    pub fn validate(&self) -> Result<()> {
+        let token_program = self.token_program.key();
+        let mint = self.token_mint_account.key();
+
+        require!(
+            self.staking_vault.to_account_info().owner == &token_program,
+            ErrorCode::InvalidTokenProgram
+        );
+        validate_token_account(
+            &self.reward_vault,
+            &self.reward_token_program.key(),
+            &self.reward_mint_account.key(),
+            &self.project.key(),
+        )?;
+        validate_withdrawal_account(&self.withdrawal_token_account, &token_program, &mint, &self.stake)?;
+        validate_withdrawal_account(
+            &self.reward_token_account,
+            &self.reward_token_program.key(),
+            &self.reward_mint_account.key(),
+            &self.stake,
+        )?;
+        validate_wallet_account(
+            &self.fee_collector_token_account,
+            &token_program,
+            &mint,
+            &self.platform.fee_collector,
+        )?;
+        validate_reflection_vault(&self.project, self.reflection_vault.as_ref())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct RequestUnstake<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+
+    /// CHECK: Optional reflection vault - checked in validate()
+    pub reflection_vault: Option<AccountInfo<'info>>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct CompleteUnstake<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+        constraint = staking_vault.mint == token_mint @ ErrorCode::WrongTokenType,
+        constraint = staking_vault.key() == project.staking_vault @ ErrorCode::UnauthorizedVault
+    )]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Can be wallet (Native SOL) or TokenAccount (SPL/Token-2022) - checked in validate()
+    #[account(mut)]
+    pub withdrawal_token_account: AccountInfo<'info>,
+
+    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL) - checked in validate()
+    #[account(mut)]
+    pub fee_collector_token_account: AccountInfo<'info>,
+
+    /// CHECK: Fee collector wallet
+    #[account(
+        mut,
+        constraint = fee_collector.key() == platform.fee_collector @ ErrorCode::InvalidFeeCollector
+    )]
+    pub fee_collector: AccountInfo<'info>,
+
+    #[account(
+        constraint = token_mint_account.key() == project.token_mint @ ErrorCode::WrongTokenType
+    )]
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct CloseStake<'info> {
+    /// CHECK: Only the address is used, so the stake can close after close_project
+    #[account(
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump
+    )]
+    pub project: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct Compound<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+
+    /// CHECK: Project staking vault - owner, mint and authority checked in validate()
+    #[account(
+        mut,
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+        constraint = staking_vault.key() == project.staking_vault @ ErrorCode::InvalidStakingVault
+    )]
+    pub staking_vault: AccountInfo<'info>,
+
+    /// CHECK: Project reward vault - owner, mint and authority checked in validate()
+    #[account(
+        mut,
+        seeds = [b"reward_vault", project.key().as_ref()],
+        bump,
+        constraint = reward_vault.key() == project.reward_vault @ ErrorCode::InvalidRewardVault
+    )]
+    pub reward_vault: AccountInfo<'info>,
+
+    /// CHECK: Fee collector wallet
+    #[account(
+        mut,
+        constraint = fee_collector.key() == platform.fee_collector @ ErrorCode::InvalidFeeCollector
+    )]
+    pub fee_collector: AccountInfo<'info>,
+
+    /// CHECK: Optional reflection vault - checked in validate()
+    pub reflection_vault: Option<AccountInfo<'info>>,
+
+    #[account(
+        constraint = token_mint_account.key() == project.token_mint @ ErrorCode::WrongTokenType
+    )]
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Compound<'info> {
+    /// Ties both unchecked vaults to the project
+    pub fn validate(&self) -> Result<()> {
+        let token_program = self.token_program.key();
+        let mint = self.token_mint_account.key();
+
+        validate_token_account(&self.staking_vault, &token_program, &mint, &self.project.key())?;
+        validate_token_account(&self.reward_vault, &token_program, &mint, &self.project.key())?;
+        validate_reflection_vault(&self.project, self.reflection_vault.as_ref())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct AddRewardStream<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + RewardStream::INIT_SPACE,
+        seeds = [b"reward_stream", project.key().as_ref(), &[project.reward_stream_count]],
+        bump
+    )]
+    pub reward_stream: Box<Account<'info, RewardStream>>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"stream_vault", reward_stream.key().as_ref()],
+        bump,
+        token::mint = stream_mint,
+        token::authority = project,
+        token::token_program = token_program,
+    )]
+    pub stream_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub stream_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64, index: u8)]
+pub struct FundRewardStream<'info> {
+    #[account(
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == funder.key()
+            || has_role(role_registry.as_deref(), &funder.key(), ROLE_REWARD_FUNDER) @ ErrorCode::Unauthorized
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_stream", project.key().as_ref(), &[index]],
+        bump = reward_stream.bump
+    )]
+    pub reward_stream: Box<Account<'info, RewardStream>>,
+
+    #[account(
+        mut,
+        constraint = stream_vault.key() == reward_stream.vault @ ErrorCode::InvalidRewardVault
+    )]
+    pub stream_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Funder's token account for the stream mint
+    #[account(mut)]
+    pub funder_token_account: AccountInfo<'info>,
+
+    #[account(
+        constraint = stream_mint.key() == reward_stream.mint @ ErrorCode::WrongTokenType
+    )]
+    pub stream_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Project admin or a holder of ROLE_REWARD_FUNDER
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"roles", project.key().as_ref()],
+        bump = role_registry.bump,
+    )]
+    pub role_registry: Option<Account<'info, RoleRegistry>>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64, index: u8)]
+pub struct ClaimStream<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Box<Account<'info, Stake>>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_stream", project.key().as_ref(), &[index]],
+        bump = reward_stream.bump
+    )]
+    pub reward_stream: Box<Account<'info, RewardStream>>,
+
+    #[account(
+        mut,
+        constraint = stream_vault.key() == reward_stream.vault @ ErrorCode::InvalidRewardVault
+    )]
+    pub stream_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Withdrawal wallet's token account for the stream mint - checked in claim_stream
+    #[account(mut)]
+    pub user_token_account: AccountInfo<'info>,
+
+    /// CHECK: Fee collector wallet
+    #[account(
+        mut,
+        constraint = fee_collector.key() == platform.fee_collector @ ErrorCode::InvalidFeeCollector
+    )]
+    pub fee_collector: AccountInfo<'info>,
+
+    #[account(
+        constraint = stream_mint.key() == reward_stream.mint @ ErrorCode::WrongTokenType
+    )]
+    pub stream_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64, index: u8)]
+pub struct SweepRewardStream<'info> {
+    #[account(
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_stream", project.key().as_ref(), &[index]],
+        bump = reward_stream.bump
+    )]
+    pub reward_stream: Box<Account<'info, RewardStream>>,
+
+    #[account(
+        mut,
+        constraint = stream_vault.key() == reward_stream.vault @ ErrorCode::InvalidRewardVault
+    )]
+    pub stream_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Admin's token account for the stream mint - checked in sweep_reward_stream
+    #[account(mut)]
+    pub admin_token_account: AccountInfo<'info>,
+
+    #[account(
+        constraint = stream_mint.key() == reward_stream.mint @ ErrorCode::WrongTokenType
+    )]
+    pub stream_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePlatform<'info> {
+    /// CHECK: Legacy-layout platform - owner and discriminator checked in migrate_platform
+    #[account(
+        mut,
+        seeds = [b"platform_v2"],
+        bump
+    )]
+    pub platform: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct MigrateProject<'info> {
+    /// CHECK: Legacy-layout project - owner and discriminator checked in migrate_project
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump
+    )]
+    pub project: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = token_mint_account.key() == token_mint @ ErrorCode::WrongTokenType
+    )]
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct MigrateStake<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    /// CHECK: Legacy-layout stake - owner, discriminator and project checked in migrate_stake
+    #[account(mut)]
+    pub stake: UncheckedAccount<'info>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct RemainingCapacity<'info> {
+    #[account(
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct RefreshReflections<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+    
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+    
+    /// CHECK: For Native SOL = Project PDA, For SPL = Project PDA's standard ATA
+    #[account(mut)]
+    pub reflection_vault: AccountInfo<'info>,
+    
+    pub user: Signer<'info>,
+}
+
+impl<'info> RefreshReflections<'info> {
+    pub fn validate(&self) -> Result<()> {
+        validate_reflection_vault(&self.project, Some(&self.reflection_vault))
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct DepositRewards<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key()
+            || has_role(role_registry.as_deref(), &admin.key(), ROLE_REWARD_FUNDER) @ ErrorCode::Unauthorized
+    )]
+    pub project: Box<Account<'info, Project>>,
+    
+    /// CHECK: Project reward vault
+    #[account(
+        mut,
+        constraint = reward_vault.key() == project.reward_vault @ ErrorCode::InvalidRewardVault
+    )]
+    pub reward_vault: AccountInfo<'info>,
+    
+    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
+    #[account(mut)]
+    pub admin_token_account: AccountInfo<'info>,
+    
+    /// Reward mint - the staking mint unless the project has a separate reward_mint
+    #[account(
+        constraint = token_mint_account.key() == project.reward_mint_key() @ ErrorCode::WrongTokenType
+    )]
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+    
+    /// Project admin or a holder of ROLE_REWARD_FUNDER
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"roles", project.key().as_ref()],
+        bump = role_registry.bump,
+    )]
+    pub role_registry: Option<Account<'info, RoleRegistry>>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct UpdateReferrer<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Account<'info, Project>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFees<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+        constraint = platform.admin == admin.key()
+            || has_role(role_registry.as_deref(), &admin.key(), ROLE_FEE_MANAGER) @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PendingFeeChange::INIT_SPACE,
+        seeds = [b"pending_fee_change", platform.key().as_ref()],
         bump
     )]
-    pub platform: Account<'info, Platform>,
+    pub pending_fee_change: Account<'info, PendingFeeChange>,
+    
+    /// Platform admin or a holder of ROLE_FEE_MANAGER
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"roles", platform.key().as_ref()],
+        bump = role_registry.bump,
+    )]
+    pub role_registry: Option<Account<'info, RoleRegistry>>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyFeeChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_fee_change", platform.key().as_ref()],
+        bump = pending_fee_change.bump,
+        close = proposer
+    )]
+    pub pending_fee_change: Account<'info, PendingFeeChange>,
+
+    /// CHECK: Receives the PendingFeeChange rent - must be the original proposer
+    #[account(mut, address = pending_fee_change.proposer @ ErrorCode::Unauthorized)]
+    pub proposer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelFeeChange<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+        constraint = platform.admin == admin.key()
+            || has_role(role_registry.as_deref(), &admin.key(), ROLE_FEE_MANAGER) @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_fee_change", platform.key().as_ref()],
+        bump = pending_fee_change.bump,
+        close = proposer
+    )]
+    pub pending_fee_change: Account<'info, PendingFeeChange>,
+
+    /// CHECK: Receives the PendingFeeChange rent - must be the original proposer
+    #[account(mut, address = pending_fee_change.proposer @ ErrorCode::Unauthorized)]
+    pub proposer: AccountInfo<'info>,
+
+    /// Platform admin or a holder of ROLE_FEE_MANAGER
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"roles", platform.key().as_ref()],
+        bump = role_registry.bump,
+    )]
+    pub role_registry: Option<Account<'info, RoleRegistry>>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct PauseControl<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Account<'info, Project>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct PauseProject<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key()
+            || has_role(role_registry.as_deref(), &admin.key(), ROLE_PAUSER) @ ErrorCode::Unauthorized
+    )]
+    pub project: Account<'info, Project>,
+
+    /// Project admin or a holder of ROLE_PAUSER
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"roles", project.key().as_ref()],
+        bump = role_registry.bump,
+    )]
+    pub role_registry: Option<Account<'info, RoleRegistry>>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+        constraint = staking_vault.mint == token_mint @ ErrorCode::WrongTokenType,
+        constraint = staking_vault.key() == project.staking_vault @ ErrorCode::UnauthorizedVault
+    )]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Withdrawal wallet (Native SOL) or a TokenAccount owned by it - validated in instruction logic
+    #[account(mut)]
+    pub withdrawal_token_account: AccountInfo<'info>,
+
+    #[account(address = project.token_mint @ ErrorCode::WrongTokenType)]
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct EmergencyReturnStake<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), stake.user.as_ref()],
+        bump = stake.bump,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+        constraint = staking_vault.key() == project.staking_vault @ ErrorCode::UnauthorizedVault
+    )]
+    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Withdrawal wallet (Native SOL) or a TokenAccount owned by it - validated in instruction logic
+    #[account(mut)]
+    pub withdrawal_token_account: AccountInfo<'info>,
+
+    /// CHECK: Optional reflection vault - validated against project.reflection_vault
+    pub reflection_vault: Option<AccountInfo<'info>>,
+
+    #[account(address = project.token_mint @ ErrorCode::WrongTokenType)]
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct ChangeWithdrawalWallet<'info> {
+    #[account(
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump
+    )]
+    pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
+        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
+    )]
+    pub stake: Account<'info, Stake>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct EmergencyUnlockAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Account<'info, Project>,
     
     #[account(mut)]
     pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct ClaimUnclaimedTokens<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Account<'info, Project>,
     
-    /// CHECK: Fee collector wallet
-    pub fee_collector: AccountInfo<'info>,
+    /// CHECK: Must be the project's staking, reward or reflection vault
+    #[account(
+        mut,
+        constraint = vault.key() == project.staking_vault
+            || vault.key() == project.reward_vault
+            || Some(vault.key()) == project.reflection_vault @ ErrorCode::UnauthorizedVault
+    )]
+    pub vault: AccountInfo<'info>,
+    
+    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
+    #[account(mut)]
+    pub admin_token_account: AccountInfo<'info>,
+    
+    pub token_mint_account: InterfaceAccount<'info, Mint>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
     
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+impl<'info> ClaimUnclaimedTokens<'info> {
+    /// Ties the sweep destination to the admin
+    pub fn validate(&self) -> Result<()> {
+        validate_wallet_account(
+            &self.admin_token_account,
+            &self.token_program.key(),
+            &self.token_mint_account.key(),
+            &self.admin.key(),
+        )
+    }
+}
+
 #[derive(Accounts)]
 #[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct CreateProject<'info> {
+pub struct CloseProject<'info> {
     #[account(
-        init,
-        payer = admin,
-        space = 8 + Project::INIT_SPACE,
-        seeds = [b"project", token_mint.key().as_ref(), &pool_id.to_le_bytes()],
-        bump
+        mut,
+        close = admin,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
     )]
     pub project: Box<Account<'info, Project>>,
+
+    #[account(
+        mut,
+        seeds = [b"staking_vault", project.key().as_ref()],
+        bump,
+        constraint = staking_vault.key() == project.staking_vault @ ErrorCode::InvalidStakingVault
+    )]
+    pub staking_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_vault", project.key().as_ref()],
+        bump,
+        constraint = reward_vault.key() == project.reward_vault @ ErrorCode::InvalidRewardVault
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL) - checked in validate()
+    #[account(mut)]
+    pub admin_token_account: AccountInfo<'info>,
+
+    /// CHECK: Receives leftover rewards - same as admin_token_account for single-mint pools, checked in validate()
+    #[account(mut)]
+    pub admin_reward_account: AccountInfo<'info>,
+
+    /// CHECK: Optional SPL reflection vault - checked against the project in close_project
+    #[account(mut)]
+    pub reflection_vault: Option<AccountInfo<'info>>,
+
+    /// CHECK: Optional admin reflection account - checked in close_project
+    #[account(mut)]
+    pub admin_reflection_account: Option<AccountInfo<'info>>,
+
+    pub reflection_token_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    pub reflection_token_program: Option<Interface<'info, TokenInterface>>,
+
+    #[account(
+        constraint = token_mint_account.key() == project.token_mint @ ErrorCode::WrongTokenType
+    )]
+    pub token_mint_account: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        constraint = reward_mint_account.key() == project.reward_mint_key() @ ErrorCode::WrongTokenType
+    )]
+    pub reward_mint_account: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CloseProject<'info> {
+    /// Ties the vaults and the sweep destination to the project and its admin
+    pub fn validate(&self) -> Result<()> {
+        let token_program = self.token_program.key();
+        let mint = self.token_mint_account.key();
+
+        validate_token_account(
+            &self.staking_vault.to_account_info(),
+            &token_program,
+            &mint,
+            &self.project.key(),
+        )?;
+        validate_token_account(
+            &self.reward_vault.to_account_info(),
+            &self.reward_token_program.key(),
+            &self.reward_mint_account.key(),
+            &self.project.key(),
+        )?;
+        validate_wallet_account(&self.admin_token_account, &token_program, &mint, &self.admin.key())?;
+        validate_wallet_account(
+            &self.admin_reward_account,
+            &self.reward_token_program.key(),
+            &self.reward_mint_account.key(),
+            &self.admin.key(),
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateFeeCollector<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+        constraint = platform.admin == admin.key()
+            || has_role(role_registry.as_deref(), &admin.key(), ROLE_FEE_MANAGER) @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
     
+    /// Platform admin or a holder of ROLE_FEE_MANAGER
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"roles", platform.key().as_ref()],
+        bump = role_registry.bump,
+    )]
+    pub role_registry: Option<Account<'info, RoleRegistry>>,
+}
+
+#[derive(Accounts)]
+pub struct ProposePlatformAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+        constraint = platform.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptPlatformAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+        constraint = platform.pending_admin.is_some() @ ErrorCode::NoPendingAdmin,
+        constraint = platform.pending_admin == Some(new_admin.key()) @ ErrorCode::NotPendingAdmin
+    )]
+    pub platform: Account<'info, Platform>,
+
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct ProposeProjectAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Account<'info, Project>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct AcceptProjectAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.pending_admin.is_some() @ ErrorCode::NoPendingAdmin,
+        constraint = project.pending_admin == Some(new_admin.key()) @ ErrorCode::NotPendingAdmin
+    )]
+    pub project: Account<'info, Project>,
+
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGlobalPause<'info> {
+    #[account(
+        mut,
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+        constraint = platform.admin == authority.key()
+            || has_role(role_registry.as_deref(), &authority.key(), ROLE_PAUSER) @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    /// Platform admin or a holder of ROLE_PAUSER (guardian)
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"roles", platform.key().as_ref()],
+        bump = role_registry.bump,
+    )]
+    pub role_registry: Option<Account<'info, RoleRegistry>>,
+}
+
+#[derive(Accounts)]
+pub struct GrantPlatformRole<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+        constraint = platform.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + RoleRegistry::INIT_SPACE,
+        seeds = [b"roles", platform.key().as_ref()],
+        bump
+    )]
+    pub role_registry: Account<'info, RoleRegistry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokePlatformRole<'info> {
+    #[account(
+        seeds = [b"platform_v2"],
+        bump = platform.bump,
+        constraint = platform.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub platform: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [b"roles", platform.key().as_ref()],
+        bump = role_registry.bump,
+    )]
+    pub role_registry: Account<'info, RoleRegistry>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct GrantProjectRole<'info> {
+    #[account(
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub project: Account<'info, Project>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + RoleRegistry::INIT_SPACE,
+        seeds = [b"roles", project.key().as_ref()],
+        bump
+    )]
+    pub role_registry: Account<'info, RoleRegistry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, pool_id: u64)]
+pub struct RevokeProjectRole<'info> {
     #[account(
-        init,
-        payer = admin,
-        seeds = [b"staking_vault", project.key().as_ref()],
-        bump,
-        token::mint = token_mint,
-        token::authority = project,
-        token::token_program = token_program,
+        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
+        bump = project.bump,
+        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
     )]
-    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
-    
+    pub project: Account<'info, Project>,
+
     #[account(
-        init,
-        payer = admin,
-        seeds = [b"reward_vault", project.key().as_ref()],
-        bump,
-        token::mint = token_mint,
-        token::authority = project,
-        token::token_program = token_program,
+        mut,
+        seeds = [b"roles", project.key().as_ref()],
+        bump = role_registry.bump,
     )]
-    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub role_registry: Account<'info, RoleRegistry>,
+
+    pub admin: Signer<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Platform {
+    pub admin: Pubkey,
+    pub fee_collector: Pubkey,
+    pub platform_token_fee_bps: u64,
+    pub platform_sol_fee: u64,
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub pending_admin: Option<Pubkey>,
+    pub global_pause: u8,
+}
+
+#[account]
+#[derive(InitSpace, Default)]
+pub struct Project {
+    pub admin: Pubkey,
+    pub token_mint: Pubkey,
+    pub pool_id: u64,
+    pub staking_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub reflection_vault: Option<Pubkey>,
+    pub reflection_token: Option<Pubkey>,
     
-    pub token_mint: InterfaceAccount<'info, Mint>,
+    pub total_staked: u64,
+    pub total_rewards_deposited: u64,
+    pub total_rewards_claimed: u64,
     
-    #[account(mut)]
-    pub admin: Signer<'info>,
+    pub rate_bps_per_year: u64,
+    pub rate_mode: u8,
+    pub reward_rate_per_second: u64,
+    pub lockup_seconds: u64,
+    pub pool_duration_seconds: u64,
+    pub pool_start_time: i64,
+    pub pool_end_time: i64,
     
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
+    pub last_update_time: i64,
+    /// Deprecated, superseded by acc_reward_per_token. Kept for the account layout
+    /// and never written.
+    pub reward_per_token_stored: u64,
+    pub reflection_per_token_stored: u64,
+    pub last_reflection_update_time: i64,
+    pub last_reflection_balance: u64,
+    
+    pub referrer: Option<Pubkey>,
+    pub referrer_split_bps: u64,
+    
+    pub is_paused: bool,
+    pub deposit_paused: bool,
+    pub withdraw_paused: bool,
+    pub claim_paused: bool,
+    pub is_initialized: bool,
+    pub enable_reflections: bool,
+    
+    pub bump: u8,
+    pub total_reflection_debt: u64,
+    pub pending_admin: Option<Pubkey>,
+    pub emergency_withdraw_enabled: bool,
+    pub total_rewards_accrued: u64,
+    pub withdrawal_wallet_delay_seconds: u64,
+    pub compound_resets_lock: bool,
+    pub reward_mint: Option<Pubkey>,
+    pub token_decimals: u8,
+    pub reward_decimals: u8,
+    pub reward_stream_count: u8,
+    /// Reward per unit of weight, scaled by REWARD_PRECISION: variable-pool rewards
+    /// and redistributed early exit penalties
+    pub acc_reward_per_token: u128,
+    /// Sum of amount × reward_rate_snapshot over all stakes (fixed-APY accrual)
+    pub total_rate_weight: u128,
+    /// Accrued rewards plus what is still scheduled to accrue until pool_end_time
+    pub projected_liability: u64,
+    /// Accept deposits before pool_start_time (they earn nothing until then)
+    pub allow_early_deposits: bool,
+    pub lock_tiers: [LockTier; MAX_LOCK_TIERS],
+    pub lock_tier_count: u8,
+    /// Sum of stake weights (amount × tier multiplier); drives reward share
+    pub total_weight: u64,
+    pub lock_mode: u8,
+    /// Set by emergency_unlock; every lock is treated as expired
+    pub locks_released: bool,
+    pub early_exit_penalty_bps: u64,
+    /// Penalty falls linearly to zero at unlock
+    pub early_exit_penalty_decays: bool,
+    /// PENALTY_TO_* destination
+    pub penalty_destination: u8,
+    /// Cooldown for request_unstake; non-zero disables withdraw and exit
+    pub unbonding_seconds: u64,
+    /// Principal requested out of stakes but not yet withdrawn
+    pub total_unbonding: u64,
+    /// Rewards credited to stakes but not yet paid out or forfeited
+    pub total_rewards_pending: u64,
+    /// Reflections credited to stakes but not yet paid out or forfeited
+    pub total_reflections_pending: u64,
+    /// Reflections distributed to stakers, credited or not, that are not yet paid out
+    /// or forfeited
+    pub total_reflections_unpaid: u64,
+    /// Set by migrate_project: legacy stakes earn under the old formula up to this time
+    pub legacy_cutoff_time: i64,
+    /// total_staked at legacy_cutoff_time, shared by the old variable-pool formula
+    pub legacy_total_staked: u64,
+    /// reward_rate_per_second at legacy_cutoff_time
+    pub legacy_reward_rate: u64,
+}
+
+impl Project {
+    /// Mint rewards are paid in - the staking mint unless a separate reward_mint was set
+    pub fn reward_mint_key(&self) -> Pubkey {
+        self.reward_mint.unwrap_or(self.token_mint)
+    }
+
+    /// Lock terms for a tier index. Pools without tiers have a single tier 0
+    /// using lockup_seconds at 1x.
+    pub fn lock_tier_terms(&self, index: u8) -> Result<LockTier> {
+        if self.lock_tier_count == 0 {
+            require!(index == 0, ErrorCode::InvalidLockTier);
+            return Ok(LockTier {
+                lockup_seconds: self.lockup_seconds,
+                multiplier_bps: BASE_MULTIPLIER_BPS,
+            });
+        }
+        require!(index < self.lock_tier_count, ErrorCode::InvalidLockTier);
+        Ok(self.lock_tiers[index as usize])
+    }
+
+    /// Lockup of a stake's tier, falling back to lockup_seconds
+    pub fn tier_lockup_seconds(&self, index: u8) -> u64 {
+        self.lock_tier_terms(index)
+            .map_or(self.lockup_seconds, |tier| tier.lockup_seconds)
+    }
+}
+
+#[account]
+#[derive(InitSpace, Default)]
+pub struct Stake {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub amount: u64,
+    pub last_stake_timestamp: i64,
+    pub withdrawal_wallet: Pubkey,
+    /// Deprecated, superseded by acc_reward_per_token_paid. Kept for the account
+    /// layout and never written.
+    pub reward_per_token_paid: u64,
+    pub rewards_pending: u64,
+    pub total_rewards_claimed: u64,
+    pub reflection_per_token_paid: u64,
+    pub reflections_pending: u64,
+    pub total_reflections_claimed: u64,
+    pub reflection_debt: u64,
+    pub reward_rate_snapshot: u64,
+    pub bump: u8,
+    pub pending_withdrawal_wallet: Option<Pubkey>,
+    pub withdrawal_wallet_effective_time: i64,
+    pub stream_checkpoints: [StreamCheckpoint; MAX_REWARD_STREAMS],
+    /// Project.acc_reward_per_token at this stake's last reward update
+    pub acc_reward_per_token_paid: u128,
+    /// Reward accrual checkpoint; last_stake_timestamp is only the lock start
+    pub last_reward_update: i64,
+    pub lock_tier: u8,
+    /// amount × tier multiplier
+    pub weight: u64,
+    /// Per-deposit unlock times (lots and weighted-average lock modes)
+    pub lots: [StakeLot; MAX_STAKE_LOTS],
+    pub lot_count: u8,
+    /// Principal waiting out the unbonding cooldown; earns nothing
+    pub unbonding_amount: u64,
+    pub unbonding_ready_time: i64,
+}
+
+/// Fee change queued by set_fees, applied by apply_fee_change
+#[account]
+#[derive(InitSpace)]
+pub struct PendingFeeChange {
+    pub platform_token_fee_bps: u64,
+    pub platform_sol_fee: u64,
+    pub effective_time: i64,
+    pub proposer: Pubkey,
+    pub bump: u8,
+}
+
+/// Per-Platform or per-Project registry of delegated roles
+#[account]
+#[derive(InitSpace)]
+pub struct RoleRegistry {
+    pub target: Pubkey,
+    #[max_len(MAX_ROLE_MEMBERS)]
+    pub members: Vec<RoleMember>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct RoleMember {
+    pub member: Pubkey,
+    pub roles: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct RewardStream {
+    pub project: Pubkey,
+    pub index: u8,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub reward_rate_per_second: u64,
+    pub reward_per_token_stored: u128,
+    pub last_update_time: i64,
+    pub end_time: i64,
+    pub total_funded: u64,
+    pub total_accrued: u64,
+    pub total_claimed: u64,
+    pub bump: u8,
+    /// Credited to stake checkpoints but not yet claimed or forfeited
+    pub total_pending: u64,
+}
+
+/// Per-stake position in a RewardStream, indexed by stream index
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct StreamCheckpoint {
+    pub reward_per_token_paid: u128,
+    pub rewards_pending: u64,
+}
+
+/// Part of a stake that unlocks at its own time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct StakeLot {
+    pub amount: u64,
+    pub unlock_time: i64,
+}
+
+/// Lock duration and reward multiplier a depositor can choose
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct LockTier {
+    pub lockup_seconds: u64,
+    pub multiplier_bps: u64,
+}
+
+impl RoleRegistry {
+    pub fn has_role(&self, member: &Pubkey, role: u8) -> bool {
+        self.members
+            .iter()
+            .any(|entry| entry.member == *member && entry.roles & role != 0)
+    }
+
+    pub fn grant(&mut self, member: Pubkey, role: u8) -> Result<()> {
+        if let Some(entry) = self.members.iter_mut().find(|entry| entry.member == member) {
+            entry.roles |= role;
+            return Ok(());
+        }
+
+        require!(self.members.len() < MAX_ROLE_MEMBERS, ErrorCode::RoleRegistryFull);
+        self.members.push(RoleMember { member, roles: role });
+
+        Ok(())
+    }
+
+    pub fn revoke(&mut self, member: &Pubkey, role: u8) -> Result<()> {
+        let index = self.members
+            .iter()
+            .position(|entry| entry.member == *member && entry.roles & role != 0)
+            .ok_or(ErrorCode::RoleNotAssigned)?;
+
+        self.members[index].roles &= !role;
+        if self.members[index].roles == 0 {
+            self.members.remove(index);
+        }
+
+        Ok(())
+    }
+}
+
+// Fields are Borsh-encoded in order; changes to this layout are listed under
+// "Breaking changes" in the README
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitializePoolParams {
+    pub rate_bps_per_year: u64,
+    pub rate_mode: u8,
+    pub lockup_seconds: u64,
+    pub pool_duration_seconds: u64,
+    pub referrer: Option<Pubkey>,
+    pub referrer_split_bps: Option<u64>,
+    pub enable_reflections: bool,
+    pub reflection_token: Option<Pubkey>,  // ← ADD THIS LINE
+    /// Launch time; defaults to now
+    pub start_time: Option<i64>,
+    pub allow_early_deposits: bool,
+    /// 0 keeps the hard lock
+    pub early_exit_penalty_bps: u64,
+    pub early_exit_penalty_decays: bool,
+    pub penalty_destination: u8,
+}
+
+#[event]
+pub struct PlatformInitialized {
+    pub admin: Pubkey,
+    pub fee_collector: Pubkey,
+    pub platform_token_fee_bps: u64,
+    pub platform_sol_fee: u64,
+}
+
+#[event]
+pub struct ProjectCreated {
+    pub project: Pubkey,
+    pub token_mint: Pubkey,
+    pub staking_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub admin: Pubkey,
+    pub reward_mint: Pubkey,
+}
+
+#[event]
+pub struct PoolInitialized {
+    pub project: Pubkey,
+    pub staking_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub rate_bps_per_year: u64,
+    pub rate_mode: u8,
+    pub lockup_seconds: u64,
+    pub pool_duration_seconds: u64,
+    pub reflections_enabled: bool,
+    pub pool_start_time: i64,
+    pub allow_early_deposits: bool,
+}
+
+#[event]
+pub struct TokensDeposited {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub amount: u64,
+    pub token_fee: u64,
+    pub sol_fee: u64,
+    pub new_total: u64,
+}
+
+#[event]
+pub struct TokensWithdrawn {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+}
+
+#[event]
+pub struct RewardsClaimed {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReflectionsClaimed {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct RewardsDeposited {
+    pub project: Pubkey,
+    pub amount: u64,
+    pub total_rewards: u64,
+    pub reward_rate: u64,
+}
+
+#[event]
+pub struct ReferrerUpdated {
+    pub project: Pubkey,
+    pub referrer: Option<Pubkey>,
+    pub split_bps: u64,
+}
+
+#[event]
+pub struct FeesUpdated {
+    pub platform_token_fee_bps: u64,
+    pub platform_sol_fee: u64,
+}
+
+#[event]
+pub struct FeeChangeQueued {
+    pub platform_token_fee_bps: u64,
+    pub platform_sol_fee: u64,
+    pub effective_time: i64,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64, params: InitializePoolParams)]
-pub struct InitializePool<'info> {
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump,
-        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
-    )]
-    pub project: Box<Account<'info, Project>>,
-    
-    #[account(
-        seeds = [b"staking_vault", project.key().as_ref()],
-        bump,
-    )]
-    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
-    
-    /// CHECK: Optional reflection token mint - validated in instruction logic
-    pub reflection_token_mint: Option<AccountInfo<'info>>,
-    
-    /// CHECK: Reflection ATA - manually validated/created
-    #[account(mut)]
-    pub reflection_token_account: Option<AccountInfo<'info>>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    
-    /// CHECK: Only used if reflections enabled
-    pub associated_token_program: Option<AccountInfo<'info>>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Interface<'info, TokenInterface>,
-    pub reflection_token_program: Option<AccountInfo<'info>>,
+#[event]
+pub struct FeeChangeCancelled {
+    pub platform_token_fee_bps: u64,
+    pub platform_sol_fee: u64,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct Deposit<'info> {
-    #[account(
-        seeds = [b"platform_v2"],
-        bump = platform.bump,
-    )]
-    pub platform: Account<'info, Platform>,
-    
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump
-    )]
-    pub project: Box<Account<'info, Project>>,
-    
-    #[account(
-        init_if_needed,
-        payer = user,
-        space = 8 + Stake::INIT_SPACE,
-        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
-        bump
-    )]
-    pub stake: Account<'info, Stake>,
-    
-    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
-    #[account(mut)]
-    pub staking_vault: AccountInfo<'info>,
-    
-    /// CHECK: Can be wallet (Native SOL) or TokenAccount (SPL)
-    #[account(mut)]
-    pub user_token_account: AccountInfo<'info>,
-        
-    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
-    #[account(mut)]
-    pub fee_collector_token_account: AccountInfo<'info>,
-        
-    /// CHECK: Fee collector wallet
-    #[account(mut)]
-    pub fee_collector: AccountInfo<'info>,
-        
-    /// CHECK: Optional reflection vault
-    pub reflection_vault: Option<AccountInfo<'info>>,    
-    pub token_mint_account: InterfaceAccount<'info, Mint>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct DepositsPaused {
+    pub project: Pubkey,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct Withdraw<'info> {
-    #[account(
-        seeds = [b"platform_v2"],
-        bump = platform.bump,
-    )]
-    pub platform: Account<'info, Platform>,
-    
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump
-    )]
-    pub project: Box<Account<'info, Project>>,
-    
-    #[account(
-        mut,
-        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
-        bump = stake.bump,
-        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
-        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
-    )]
-    pub stake: Account<'info, Stake>,
-    
-    #[account(
-        mut,
-        seeds = [b"staking_vault", project.key().as_ref()],
-        bump,
-        constraint = staking_vault.mint == token_mint @ ErrorCode::WrongTokenType,
-        constraint = staking_vault.key() == project.staking_vault @ ErrorCode::UnauthorizedVault
-    )]
-    pub staking_vault: InterfaceAccount<'info, TokenAccount>,
-    
-    /// CHECK: Can be wallet (Native SOL) or TokenAccount (SPL/Token-2022)
-    /// Ownership validated in transfer_tokens function
-    #[account(mut)]
-    pub withdrawal_token_account: AccountInfo<'info>,
+#[event]
+pub struct DepositsUnpaused {
+    pub project: Pubkey,
+}
 
-    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
-    #[account(mut)]
-    pub fee_collector_token_account: AccountInfo<'info>,
-        
-    /// CHECK: Fee collector wallet
-    #[account(mut)]
-    pub fee_collector: AccountInfo<'info>,
-    
-    /// CHECK: Optional reflection vault - can be staking_vault for Native SOL or ATA for SPL tokens
-    pub reflection_vault: Option<AccountInfo<'info>>,
-    
-    pub token_mint_account: InterfaceAccount<'info, Mint>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct WithdrawalsPaused {
+    pub project: Pubkey,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct Claim<'info> {
-    #[account(
-        seeds = [b"platform_v2"],
-        bump = platform.bump,
-    )]
-    pub platform: Account<'info, Platform>,
-    
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump
-    )]
-    pub project: Box<Account<'info, Project>>,
-    
-    #[account(
-        mut,
-        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
-        bump = stake.bump,
-        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
-        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
-    )]
-    pub stake: Account<'info, Stake>,
-    
-    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
-    #[account(mut)]
-    pub reward_vault: AccountInfo<'info>,
-        
-    /// CHECK: Can be wallet (Native SOL) or TokenAccount (SPL)
-    #[account(mut)]
-    pub user_token_account: AccountInfo<'info>,
-    
-    /// CHECK: Fee collector wallet
-    #[account(mut)]
-    pub fee_collector: AccountInfo<'info>,
-    
-    /// CHECK: Optional reflection vault
-    pub reflection_vault: Option<AccountInfo<'info>>,    
-    pub token_mint_account: InterfaceAccount<'info, Mint>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct WithdrawalsUnpaused {
+    pub project: Pubkey,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct ClaimReflections<'info> {
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump
-    )]
-    pub project: Box<Account<'info, Project>>,
-    
-    #[account(
-        mut,
-        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
-        bump = stake.bump,
-        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
-        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
-    )]
-    pub stake: Account<'info, Stake>,
+#[event]
+pub struct ClaimsPaused {
+    pub project: Pubkey,
+}
 
-    /// CHECK: For Native SOL = Project PDA, For SPL = Project PDA's standard ATA
-    #[account(mut)]
-    pub reflection_vault: AccountInfo<'info>,
-    
-    /// CHECK: User's wallet (for Native SOL) or user's ATA (for SPL)
-    #[account(mut)]
-    pub user_reflection_account: AccountInfo<'info>,
-    
-    pub reflection_token_mint: InterfaceAccount<'info, Mint>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct ClaimsUnpaused {
+    pub project: Pubkey,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct RefreshReflections<'info> {
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump
-    )]
-    pub project: Box<Account<'info, Project>>,
-    
-    #[account(
-        mut,
-        seeds = [b"stake", project.key().as_ref(), user.key().as_ref()],
-        bump = stake.bump,
-        constraint = stake.user == user.key() @ ErrorCode::Unauthorized,
-        constraint = stake.project == project.key() @ ErrorCode::InvalidProject
-    )]
-    pub stake: Account<'info, Stake>,
-    
-    /// CHECK: For Native SOL = Project PDA, For SPL = Project PDA's standard ATA
-    #[account(mut)]
-    pub reflection_vault: AccountInfo<'info>,
-    
-    pub user: Signer<'info>,
+#[event]
+pub struct PlatformPaused {
+    pub global_pause: u8,
+    pub reason_code: u16,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ProjectPaused {
+    pub project: Pubkey,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct DepositRewards<'info> {
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump,
-        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
-    )]
-    pub project: Box<Account<'info, Project>>,
-    
-    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
-    #[account(mut)]
-    pub reward_vault: AccountInfo<'info>,
-    
-    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
-    #[account(mut)]
-    pub admin_token_account: AccountInfo<'info>,
-    
-    pub token_mint_account: InterfaceAccount<'info, Mint>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct ProjectUnpaused {
+    pub project: Pubkey,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct UpdateReferrer<'info> {
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump,
-        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
-    )]
-    pub project: Account<'info, Project>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
+#[event]
+pub struct EmergencyUnlockEvent {
+    pub project: Pubkey,
+    pub admin: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct SetFees<'info> {
-    #[account(
-        mut,
-        seeds = [b"platform_v2"],
-        bump = platform.bump,
-        constraint = platform.admin == admin.key() @ ErrorCode::Unauthorized
-    )]
-    pub platform: Account<'info, Platform>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
+#[event]
+pub struct EmergencyWithdrawToggled {
+    pub project: Pubkey,
+    pub enabled: bool,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct PauseControl<'info> {
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump,
-        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
-    )]
-    pub project: Account<'info, Project>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
+#[event]
+pub struct EmergencyWithdrawn {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub amount: u64,
+    pub rewards_forfeited: u64,
+    pub reflections_forfeited: u64,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct EmergencyUnlockAccounts<'info> {
-    #[account(
-        mut,
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump,
-        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
-    )]
-    pub project: Account<'info, Project>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
+#[event]
+pub struct EmergencyStakeReturned {
+    pub project: Pubkey,
+    pub user: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
 }
 
-#[derive(Accounts)]
-#[instruction(token_mint: Pubkey, pool_id: u64)]
-pub struct ClaimUnclaimedTokens<'info> {
-    #[account(
-        seeds = [b"project", token_mint.as_ref(), &pool_id.to_le_bytes()],
-        bump = project.bump,
-        constraint = project.admin == admin.key() @ ErrorCode::Unauthorized
-    )]
-    pub project: Account<'info, Project>,
-    
-    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
-    #[account(mut)]
-    pub vault: AccountInfo<'info>,
-    
-    /// CHECK: Can be TokenAccount (SPL) or wallet (Native SOL)
-    #[account(mut)]
-    pub admin_token_account: AccountInfo<'info>,
-    
-    pub token_mint_account: InterfaceAccount<'info, Mint>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    
-    pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct WithdrawalWalletDelayUpdated {
+    pub project: Pubkey,
+    pub delay_seconds: u64,
 }
 
-#[derive(Accounts)]
-pub struct UpdateFeeCollector<'info> {
-    #[account(
-        mut,
-        seeds = [b"platform_v2"],
-        bump = platform.bump,
-        constraint = platform.admin == admin.key() @ ErrorCode::Unauthorized
-    )]
-    pub platform: Account<'info, Platform>,
-    
-    #[account(mut)]
-    pub admin: Signer<'info>,
+#[event]
+pub struct WithdrawalWalletChangeQueued {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub new_wallet: Pubkey,
+    pub effective_time: i64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Platform {
-    pub admin: Pubkey,
-    pub fee_collector: Pubkey,
-    pub platform_token_fee_bps: u64,
-    pub platform_sol_fee: u64,
-    pub is_initialized: bool,
-    pub bump: u8,
+#[event]
+pub struct WithdrawalWalletChanged {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub new_wallet: Pubkey,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Project {
+#[event]
+pub struct UnclaimedTokensSwept {
+    pub project: Pubkey,
+    pub vault: Pubkey,
     pub admin: Pubkey,
-    pub token_mint: Pubkey,
-    pub pool_id: u64,
-    pub staking_vault: Pubkey,
-    pub reward_vault: Pubkey,
-    pub reflection_vault: Option<Pubkey>,
-    pub reflection_token: Option<Pubkey>,
-    
-    pub total_staked: u64,
-    pub total_rewards_deposited: u64,
-    pub total_rewards_claimed: u64,
-    
+    pub amount: u64,
+}
+
+#[event]
+pub struct StakeExited {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub amount: u64,
+    pub rewards: u64,
+    pub reflections: u64,
+}
+
+#[event]
+pub struct StakeClosed {
+    pub user: Pubkey,
+    pub project: Pubkey,
+}
+
+#[event]
+pub struct RewardsCompounded {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub rewards: u64,
+    pub amount: u64,
+    pub new_total: u64,
+}
+
+#[event]
+pub struct CompoundLockConfigUpdated {
+    pub project: Pubkey,
+    pub resets_lock: bool,
+}
+
+#[event]
+pub struct PoolRateUpdated {
+    pub project: Pubkey,
+    pub old_rate_bps_per_year: u64,
     pub rate_bps_per_year: u64,
-    pub rate_mode: u8,
     pub reward_rate_per_second: u64,
-    pub lockup_seconds: u64,
-    pub pool_duration_seconds: u64,
-    pub pool_start_time: i64,
-    pub pool_end_time: i64,
-    
-    pub last_update_time: i64,
-    pub reward_per_token_stored: u64,
-    pub reflection_per_token_stored: u64,
-    pub last_reflection_update_time: i64,
-    pub last_reflection_balance: u64,
-    
-    pub referrer: Option<Pubkey>,
-    pub referrer_split_bps: u64,
-    
-    pub is_paused: bool,
-    pub deposit_paused: bool,
-    pub withdraw_paused: bool,
-    pub claim_paused: bool,
-    pub is_initialized: bool,
-    pub enable_reflections: bool,
-    
-    pub bump: u8,
-    pub total_reflection_debt: u64,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Stake {
+#[event]
+pub struct UnbondingPeriodUpdated {
+    pub project: Pubkey,
+    pub unbonding_seconds: u64,
+}
+
+#[event]
+pub struct UnstakeRequested {
     pub user: Pubkey,
     pub project: Pubkey,
     pub amount: u64,
-    pub last_stake_timestamp: i64,
-    pub withdrawal_wallet: Pubkey,
-    pub reward_per_token_paid: u64,
-    pub rewards_pending: u64,
-    pub total_rewards_claimed: u64,
-    pub reflection_per_token_paid: u64,
-    pub reflections_pending: u64,
-    pub total_reflections_claimed: u64,
-    pub reflection_debt: u64,
-    pub reward_rate_snapshot: u64,
-    pub bump: u8,
+    pub unbonding_amount: u64,
+    pub ready_time: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct InitializePoolParams {
-    pub rate_bps_per_year: u64,
-    pub rate_mode: u8,
-    pub lockup_seconds: u64,
-    pub pool_duration_seconds: u64,
-    pub referrer: Option<Pubkey>,
-    pub referrer_split_bps: Option<u64>,
-    pub enable_reflections: bool,
-    pub reflection_token: Option<Pubkey>,  // ← ADD THIS LINE
+#[event]
+pub struct UnstakeCompleted {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub amount: u64,
+    pub token_fee: u64,
+}
+
+#[event]
+pub struct UnstakeCancelled {
+    pub user: Pubkey,
+    pub project: Pubkey,
+    pub amount: u64,
+    pub new_total: u64,
 }
 
 #[event]
-pub struct PlatformInitialized {
-    pub admin: Pubkey,
-    pub fee_collector: Pubkey,
-    pub platform_token_fee_bps: u64,
-    pub platform_sol_fee: u64,
+pub struct EarlyExitPenaltyUpdated {
+    pub project: Pubkey,
+    pub penalty_bps: u64,
+    pub decays: bool,
+    pub destination: u8,
 }
 
 #[event]
-pub struct ProjectCreated {
+pub struct EarlyExitPenaltyCharged {
+    pub user: Pubkey,
     pub project: Pubkey,
-    pub token_mint: Pubkey,
-    pub staking_vault: Pubkey,
-    pub reward_vault: Pubkey,
-    pub admin: Pubkey,
+    pub amount: u64,
+    pub penalty: u64,
+    pub destination: u8,
 }
 
 #[event]
-pub struct PoolInitialized {
+pub struct LockModeUpdated {
     pub project: Pubkey,
-    pub staking_vault: Pubkey,
-    pub reward_vault: Pubkey,
-    pub rate_bps_per_year: u64,
-    pub rate_mode: u8,
-    pub lockup_seconds: u64,
-    pub pool_duration_seconds: u64,
-    pub reflections_enabled: bool,
+    pub lock_mode: u8,
 }
 
 #[event]
-pub struct TokensDeposited {
-    pub user: Pubkey,
+pub struct LockTiersUpdated {
     pub project: Pubkey,
-    pub amount: u64,
-    pub token_fee: u64,
-    pub sol_fee: u64,
-    pub new_total: u64,
+    pub tiers: Vec<LockTier>,
 }
 
 #[event]
-pub struct TokensWithdrawn {
-    pub user: Pubkey,
+pub struct PoolEndTimeUpdated {
     pub project: Pubkey,
-    pub amount: u64,
-    pub remaining: u64,
+    pub old_end_time: i64,
+    pub new_end_time: i64,
+    pub reward_rate_per_second: u64,
 }
 
 #[event]
-pub struct RewardsClaimed {
+pub struct StakeRateMigrated {
     pub user: Pubkey,
     pub project: Pubkey,
-    pub amount: u64,
+    pub old_rate: u64,
+    pub new_rate: u64,
 }
 
 #[event]
-pub struct ReflectionsClaimed {
-    pub user: Pubkey,
+pub struct RewardStreamAdded {
     pub project: Pubkey,
+    pub stream: Pubkey,
+    pub index: u8,
+    pub mint: Pubkey,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct RewardStreamFunded {
+    pub stream: Pubkey,
+    pub index: u8,
     pub amount: u64,
+    pub total_funded: u64,
+    pub reward_rate: u64,
 }
 
 #[event]
-pub struct RewardsDeposited {
-    pub project: Pubkey,
+pub struct RewardStreamSwept {
+    pub stream: Pubkey,
+    pub index: u8,
     pub amount: u64,
-    pub total_rewards: u64,
+    pub total_funded: u64,
     pub reward_rate: u64,
 }
 
 #[event]
-pub struct ReferrerUpdated {
+pub struct StreamRewardsClaimed {
+    pub user: Pubkey,
     pub project: Pubkey,
-    pub referrer: Option<Pubkey>,
-    pub split_bps: u64,
+    pub stream: Pubkey,
+    pub amount: u64,
 }
 
 #[event]
-pub struct FeesUpdated {
-    pub platform_token_fee_bps: u64,
-    pub platform_sol_fee: u64,
+pub struct PlatformMigrated {
+    pub platform: Pubkey,
 }
 
 #[event]
-pub struct ProjectPaused {
+pub struct ProjectMigrated {
     pub project: Pubkey,
 }
 
 #[event]
-pub struct ProjectUnpaused {
+pub struct StakeMigrated {
+    pub user: Pubkey,
     pub project: Pubkey,
+    pub stake: Pubkey,
+    pub legacy_rewards: u64,
 }
 
 #[event]
-pub struct EmergencyUnlockEvent {
+pub struct ProjectClosed {
     pub project: Pubkey,
     pub admin: Pubkey,
+    pub staking_swept: u64,
+    pub rewards_swept: u64,
+    pub reflections_swept: u64,
 }
 
 #[event]
@@ -2224,6 +6807,40 @@ pub struct FeeCollectorUpdated {
     pub new_fee_collector: Pubkey,
 }
 
+#[event]
+pub struct AdminProposed {
+    pub account: Pubkey,
+    pub current_admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminAccepted {
+    pub account: Pubkey,
+    pub old_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminProposalCancelled {
+    pub account: Pubkey,
+    pub cancelled_admin: Pubkey,
+}
+
+#[event]
+pub struct RoleGranted {
+    pub target: Pubkey,
+    pub member: Pubkey,
+    pub role: u8,
+}
+
+#[event]
+pub struct RoleRevoked {
+    pub target: Pubkey,
+    pub member: Pubkey,
+    pub role: u8,
+}
+
 #[error_code]
 pub enum ErrorCode {
     #[msg("Unauthorized")]
@@ -2310,4 +6927,937 @@ pub enum ErrorCode {
     InvalidReferrerSplit,
     #[msg("No reflections to claim - refresh reflections first")]
     NoReflectionsToClaim,
-}
\ No newline at end of file
+    #[msg("No admin handover is pending")]
+    NoPendingAdmin,
+    #[msg("Signer is not the pending admin")]
+    NotPendingAdmin,
+    #[msg("Invalid pending admin - cannot be the default pubkey")]
+    InvalidPendingAdmin,
+    #[msg("Invalid role for this registry")]
+    InvalidRole,
+    #[msg("Role registry is full")]
+    RoleRegistryFull,
+    #[msg("Member does not hold this role")]
+    RoleNotAssigned,
+    #[msg("Token fee exceeds MAX_PLATFORM_TOKEN_FEE_BPS")]
+    TokenFeeTooHigh,
+    #[msg("SOL fee exceeds MAX_PLATFORM_SOL_FEE")]
+    SolFeeTooHigh,
+    #[msg("Fee change delay has not passed yet")]
+    FeeChangeNotReady,
+    #[msg("Invalid pause flags")]
+    InvalidPauseFlags,
+    #[msg("Platform is paused by the circuit breaker")]
+    GloballyPaused,
+    #[msg("Emergency withdraw is not enabled for this project")]
+    EmergencyWithdrawDisabled,
+    #[msg("Remaining accounts must be [stake, withdrawal_token_account] pairs")]
+    InvalidRemainingAccounts,
+    #[msg("Invalid stake account")]
+    InvalidStakeAccount,
+    #[msg("Token account is not owned by the expected token program")]
+    InvalidTokenProgram,
+    #[msg("Invalid token account data")]
+    InvalidTokenAccountData,
+    #[msg("Amount exceeds the vault surplus not owed to stakers")]
+    AmountExceedsSurplus,
+    #[msg("Staking vault does not match project staking vault")]
+    InvalidStakingVault,
+    #[msg("Reward vault does not match project reward vault")]
+    InvalidRewardVault,
+    #[msg("Token account mint does not match the expected mint")]
+    TokenAccountMintMismatch,
+    #[msg("Token account authority does not match the expected owner")]
+    TokenAccountAuthorityMismatch,
+    #[msg("Native SOL account must be the expected wallet")]
+    NativeWalletMismatch,
+    #[msg("No withdrawal wallet change is pending")]
+    NoPendingWithdrawalWallet,
+    #[msg("Withdrawal wallet change delay has not passed yet")]
+    WithdrawalWalletChangeNotReady,
+    #[msg("Reflection vault, payout account, mint and token program are required")]
+    ReflectionAccountsRequired,
+    #[msg("Stake still holds principal, rewards or reflections")]
+    StakeNotEmpty,
+    #[msg("Pool has not ended yet")]
+    PoolNotEnded,
+    #[msg("Pool still has staked tokens")]
+    PoolNotEmpty,
+    #[msg("Compounding requires the reward mint to match the staking mint")]
+    CompoundRequiresSameMint,
+    #[msg("All reward streams must be passed in index order")]
+    MissingRewardStreams,
+    #[msg("Invalid reward stream account")]
+    InvalidRewardStream,
+    #[msg("Project already has the maximum number of reward streams")]
+    RewardStreamLimitReached,
+    #[msg("Reward stream end time must be in the future")]
+    InvalidStreamEndTime,
+    #[msg("Reward stream has ended")]
+    StreamEnded,
+    #[msg("Native SOL reward streams are not supported")]
+    NativeStreamNotSupported,
+    #[msg("Claim reward stream rewards before closing the stake")]
+    StreamRewardsPending,
+    #[msg("Stake account already uses the current layout")]
+    StakeAlreadyMigrated,
+    #[msg("Deposited rewards cannot cover the pool's fixed-APY liability")]
+    InsufficientRewardFunding,
+    #[msg("Pool start time cannot be in the past")]
+    InvalidStartTime,
+    #[msg("Pool has not started yet")]
+    PoolNotStarted,
+    #[msg("New pool end time must be after the current time and pool start")]
+    InvalidPoolEndTime,
+    #[msg("Invalid lock tier")]
+    InvalidLockTier,
+    #[msg("Cannot move a stake to a shorter lock tier")]
+    LockTierDowngrade,
+    #[msg("Lock tiers cannot change while the pool has stakers")]
+    LockTiersFrozen,
+    #[msg("Too many lock tiers")]
+    TooManyLockTiers,
+    #[msg("Lock multiplier must be between 1x and 10x")]
+    InvalidLockMultiplier,
+    #[msg("Invalid lock mode")]
+    InvalidLockMode,
+    #[msg("Lock mode cannot change while the pool has stakers")]
+    LockModeFrozen,
+    #[msg("Early exit penalty exceeds the maximum")]
+    InvalidPenaltyBps,
+    #[msg("Invalid early exit penalty destination")]
+    InvalidPenaltyDestination,
+    #[msg("Native SOL penalties cannot be burned")]
+    NativePenaltyBurn,
+    #[msg("Penalties go to the reward vault only when rewards are paid in the staking mint")]
+    PenaltyRequiresSameMint,
+    #[msg("Reward vault required for this early exit penalty")]
+    PenaltyAccountsRequired,
+    #[msg("This pool requires request_unstake and a cooldown to withdraw")]
+    UnbondingRequired,
+    #[msg("Complete or cancel the pending unstake first")]
+    UnbondingPending,
+    #[msg("Nothing is unbonding")]
+    NothingUnbonding,
+    #[msg("Unbonding cooldown has not passed yet")]
+    UnbondingNotReady,
+    #[msg("Unbonding period exceeds the maximum")]
+    InvalidUnbondingPeriod,
+    #[msg("Invalid platform account")]
+    InvalidPlatformAccount,
+    #[msg("Account already uses the current layout")]
+    AccountAlreadyMigrated,
+    #[msg("Stakers still have unclaimed rewards or reflections")]
+    RewardsOutstanding,
+    #[msg("Migrate the project before its stakes")]
+    ProjectNotMigrated,
+    #[msg("Legacy rewards must be credited from the stake timestamp up to the project migration")]
+    InvalidLegacyCredit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u64 = 1_000;
+    const END: i64 = 1_000;
+
+    fn variable_pool() -> Project {
+        Project {
+            rate_mode: 1,
+            reward_rate_per_second: RATE,
+            pool_end_time: END,
+            ..Default::default()
+        }
+    }
+
+    // Mirrors deposit/withdraw: checkpoint rewards first, then change amounts
+    fn stake_at(project: &mut Project, stake: &mut Stake, amount: u64, now: i64) {
+        accrue_reward(project, stake, now).unwrap();
+        reprice_stake(project, stake).unwrap();
+        add_to_stake(project, stake, amount).unwrap();
+    }
+
+    fn unstake_at(project: &mut Project, stake: &mut Stake, amount: u64, now: i64) {
+        accrue_reward(project, stake, now).unwrap();
+        remove_from_stake(project, stake, amount, now).unwrap();
+    }
+
+    #[test]
+    fn late_joiner_does_not_dilute_earlier_periods() {
+        let mut project = variable_pool();
+        let mut alice = Stake::default();
+        let mut bob = Stake::default();
+
+        stake_at(&mut project, &mut alice, 100, 0);
+        stake_at(&mut project, &mut bob, 400, 500);
+        unstake_at(&mut project, &mut alice, 100, 800);
+        accrue_reward(&mut project, &mut bob, END).unwrap();
+
+        // Alice: 500s alone + 300s at 1/5; Bob: 300s at 4/5 + 200s alone
+        assert_eq!(alice.rewards_pending, 500 * RATE + 300 * RATE / 5);
+        assert_eq!(bob.rewards_pending, 300 * RATE * 4 / 5 + 200 * RATE);
+        assert_eq!(alice.rewards_pending + bob.rewards_pending, RATE * END as u64);
+        assert_eq!(project.total_rewards_pending, alice.rewards_pending + bob.rewards_pending);
+    }
+
+    #[test]
+    fn accrual_stops_at_pool_end() {
+        let mut project = variable_pool();
+        let mut stake = Stake::default();
+
+        stake_at(&mut project, &mut stake, 8, 0);
+        accrue_reward(&mut project, &mut stake, END + 5_000).unwrap();
+
+        assert_eq!(stake.rewards_pending, RATE * END as u64);
+        assert_eq!(project.total_rewards_accrued, RATE * END as u64);
+    }
+
+    #[test]
+    fn variable_pool_reserves_unaccrued_rewards() {
+        let mut project = variable_pool();
+        project.total_rewards_deposited = RATE * END as u64;
+        let mut stake = Stake::default();
+
+        // The whole schedule is committed before anything accrues, and stays committed
+        stake_at(&mut project, &mut stake, 100, 0);
+        assert_eq!(projected_liability(&project).unwrap(), RATE * END as u64);
+        accrue_reward(&mut project, &mut stake, 400).unwrap();
+        assert_eq!(projected_liability(&project).unwrap(), RATE * END as u64);
+        accrue_reward(&mut project, &mut stake, END).unwrap();
+        assert_eq!(projected_liability(&project).unwrap(), RATE * END as u64);
+    }
+
+    #[test]
+    fn stream_sweep_respreads_only_unallocated_funds() {
+        let project = variable_pool();
+        let mut stream = RewardStream {
+            project: Pubkey::default(),
+            index: 0,
+            mint: Pubkey::default(),
+            vault: Pubkey::default(),
+            reward_rate_per_second: 0,
+            reward_per_token_stored: 0,
+            last_update_time: 0,
+            end_time: END,
+            total_funded: 2 * RATE * END as u64,
+            total_accrued: 0,
+            total_claimed: 0,
+            bump: 0,
+            total_pending: 0,
+        };
+        respread_stream_rate(&mut stream, &project, 0).unwrap();
+        assert_eq!(stream.reward_rate_per_second, 2 * RATE);
+
+        // Half the window accrues to a single staker of weight 100
+        stream.total_accrued = RATE * END as u64;
+        stream.reward_per_token_stored = reward_per_token_delta(2 * RATE, 500, 100).unwrap();
+        let mut checkpoint = StreamCheckpoint::default();
+        update_stream_reward(&mut stream, &mut checkpoint, 100).unwrap();
+        assert_eq!(checkpoint.rewards_pending, RATE * END as u64);
+        assert_eq!(stream.total_pending, checkpoint.rewards_pending);
+
+        // Sweeping half of what is unallocated halves the rate for the rest of the stream
+        stream.total_funded -= RATE * END as u64 / 2;
+        respread_stream_rate(&mut stream, &project, 500).unwrap();
+        assert_eq!(stream.reward_rate_per_second, RATE);
+    }
+
+    #[test]
+    fn reward_deposits_never_respread_accrued_rewards() {
+        let mut project = variable_pool();
+        project.reward_rate_per_second = 0;
+        let mut stake = Stake::default();
+
+        credit_reward_deposit(&mut project, RATE * END as u64, 0).unwrap();
+        assert_eq!(project.reward_rate_per_second, RATE);
+        stake_at(&mut project, &mut stake, 100, 0);
+
+        // Half-way top-up: the first 500s accrue at the old rate, then only the
+        // unaccrued half plus the new funds are spread over the last 500s
+        credit_reward_deposit(&mut project, RATE * END as u64 / 2, 500).unwrap();
+        assert_eq!(project.total_rewards_accrued, 500 * RATE);
+        assert_eq!(project.reward_rate_per_second, 2 * RATE);
+
+        accrue_reward(&mut project, &mut stake, END).unwrap();
+        assert_eq!(stake.rewards_pending, project.total_rewards_deposited);
+        assert_eq!(project.total_rewards_accrued, project.total_rewards_deposited);
+    }
+
+    #[test]
+    fn emergency_forfeit_survives_broken_reward_math() {
+        let mut project = variable_pool();
+        let mut alice = Stake::default();
+        let mut bob = Stake::default();
+
+        stake_at(&mut project, &mut alice, 100, 0);
+        stake_at(&mut project, &mut bob, 100, 0);
+        accrue_reward(&mut project, &mut alice, 200).unwrap();
+        alice.reflections_pending = 7;
+        project.total_reflections_pending = 7;
+
+        // Alice's share is settled up to now and leaves the accrued total
+        let forfeited = forfeit_stake_rewards(&mut project, &mut alice, 400);
+        assert_eq!(forfeited, (400 * RATE / 2, 7));
+        assert_eq!((alice.rewards_pending, alice.reflections_pending), (0, 0));
+        assert_eq!(project.total_rewards_accrued, 400 * RATE / 2);
+        assert_eq!((project.total_rewards_pending, project.total_reflections_pending), (0, 0));
+        remove_from_stake(&mut project, &mut alice, 100, 400).unwrap();
+
+        // An overflowing accrual only releases what was already pending
+        accrue_reward(&mut project, &mut bob, 500).unwrap();
+        project.total_rewards_accrued = u64::MAX;
+        let forfeited = forfeit_stake_rewards(&mut project, &mut bob, 600);
+        assert_eq!(forfeited, (300 * RATE, 0));
+        assert_eq!(bob.rewards_pending, 0);
+        assert_eq!(project.last_update_time, 500);
+        assert_eq!(project.total_rewards_pending, 0);
+    }
+
+    #[test]
+    fn returned_stake_reflections_cannot_be_swept() {
+        let mut project = Project { enable_reflections: true, ..variable_pool() };
+        let mut alice = Stake::default();
+        let mut bob = Stake::default();
+
+        stake_at(&mut project, &mut alice, 100, 0);
+        stake_at(&mut project, &mut bob, 300, 0);
+        distribute_reflections(&mut project, 4_000, 10).unwrap();
+        assert_eq!(reflection_surplus(&project, 4_000), 0);
+
+        // emergency_return_stake credits Alice before her principal leaves
+        settle_reflections(&mut project, &mut alice, true).unwrap();
+        remove_from_stake(&mut project, &mut alice, 100, 20).unwrap();
+        assert_eq!(alice.reflections_pending, 1_000);
+        assert_eq!(reflection_surplus(&project, 4_000), 0);
+
+        // Bob's emergency_withdraw forfeits his uncredited share, which becomes surplus
+        let (_, reflections_forfeited) = forfeit_stake_rewards(&mut project, &mut bob, 30);
+        remove_from_stake(&mut project, &mut bob, 300, 30).unwrap();
+        assert_eq!(reflections_forfeited, 3_000);
+        assert_eq!(project.total_staked, 0);
+        assert_eq!(reflection_surplus(&project, 4_000), 3_000);
+
+        // Reflections arriving later wait for the next staker
+        assert_eq!(reflection_surplus(&project, 4_500), 3_000);
+    }
+
+    // A legacy pool as migrate_project finds it: stakes only counted in total_staked
+    fn legacy_pool(rate_mode: u8, rate: u64, stakes: &[&Stake]) -> Project {
+        let total_staked = stakes.iter().map(|stake| stake.amount).sum();
+        Project { rate_mode, reward_rate_per_second: rate, total_staked, ..variable_pool() }
+    }
+
+    #[test]
+    fn legacy_variable_stakes_keep_their_share_until_migrated() {
+        let mut alice = Stake { amount: 100, ..Stake::default() };
+        let mut bob = Stake { amount: 300, last_stake_timestamp: 100, ..Stake::default() };
+        let mut project = legacy_pool(1, RATE, &[&alice, &bob]);
+
+        begin_legacy_migration(&mut project, 300).unwrap();
+        let mut carol = Stake::default();
+        stake_at(&mut project, &mut carol, 100, 400);
+
+        // Bob's legacy partial withdraw already credited him up to t=200
+        assert!(register_legacy_stake(&mut project, &mut bob, 50).is_err());
+        assert!(register_legacy_stake(&mut project, &mut bob, 301).is_err());
+        assert_eq!(register_legacy_stake(&mut project, &mut alice, 0).unwrap(), 300 * RATE / 4);
+        assert_eq!(register_legacy_stake(&mut project, &mut bob, 200).unwrap(), 100 * RATE * 3 / 4);
+        assert_eq!(project.total_weight, 500);
+
+        // Migrating late costs nothing: both earned their share of [300, 400] unmigrated
+        for stake in [&mut alice, &mut bob, &mut carol] {
+            accrue_reward(&mut project, stake, END).unwrap();
+        }
+        assert_eq!(alice.rewards_pending, 300 * RATE / 4 + 100 * RATE / 4 + 600 * RATE / 5);
+        assert_eq!(bob.rewards_pending, 100 * RATE * 3 / 4 + 100 * RATE * 3 / 4 + 600 * RATE * 3 / 5);
+        assert_eq!(carol.rewards_pending, 600 * RATE / 5);
+        assert_eq!(
+            project.total_rewards_accrued,
+            alice.rewards_pending + bob.rewards_pending + carol.rewards_pending
+        );
+    }
+
+    #[test]
+    fn legacy_fixed_stakes_are_not_paid_twice() {
+        // 1000 staked earns 1 per second; a legacy withdraw credited the first 200s
+        let mut alice = Stake { amount: 1_000, rewards_pending: 200, ..Stake::default() };
+        let mut project = legacy_pool(0, 1_000_000, &[&alice]);
+        project.total_rewards_deposited = 1_000;
+
+        begin_legacy_migration(&mut project, 300).unwrap();
+        assert_eq!(project.total_rate_weight, rate_weight(1_000, 1_000_000));
+        assert_eq!(register_legacy_stake(&mut project, &mut alice, 200).unwrap(), 100);
+        assert_eq!(project.projected_liability, 1_000);
+        require_rewards_funded(&project).unwrap();
+
+        accrue_reward(&mut project, &mut alice, 700).unwrap();
+        accrue_reward(&mut project, &mut alice, END).unwrap();
+        assert_eq!(alice.rewards_pending, 1_000);
+        assert_eq!(project.total_rewards_accrued, 1_000);
+    }
+
+    #[test]
+    fn idle_periods_are_not_paid_out() {
+        let mut project = variable_pool();
+        let mut stake = Stake::default();
+
+        // Nobody staked for the first 400 seconds
+        accrue_pool_reward(&mut project, 400).unwrap();
+        stake_at(&mut project, &mut stake, 50, 400);
+        accrue_reward(&mut project, &mut stake, END).unwrap();
+
+        assert_eq!(stake.rewards_pending, 600 * RATE);
+    }
+
+    #[test]
+    fn fixed_rate_change_is_grandfathered_until_top_up() {
+        let mut project = Project { rate_mode: 0, reward_rate_per_second: 1_000_000, ..variable_pool() };
+        let mut alice = Stake::default();
+        let mut bob = Stake::default();
+
+        stake_at(&mut project, &mut alice, 1_000, 0);
+        // set_pool_rate: checkpoint, then double the rate
+        accrue_pool_reward(&mut project, 200).unwrap();
+        project.reward_rate_per_second = 2_000_000;
+        stake_at(&mut project, &mut bob, 1_000, 200);
+
+        accrue_reward(&mut project, &mut alice, 400).unwrap();
+        accrue_reward(&mut project, &mut bob, 400).unwrap();
+        assert_eq!(alice.rewards_pending, 400);
+        assert_eq!(bob.rewards_pending, 400);
+
+        // Alice tops up at 400 and moves to the new rate
+        stake_at(&mut project, &mut alice, 1_000, 400);
+        assert_eq!(alice.reward_rate_snapshot, 2_000_000);
+        accrue_reward(&mut project, &mut alice, 500).unwrap();
+        accrue_reward(&mut project, &mut bob, 500).unwrap();
+
+        assert_eq!(alice.rewards_pending, 400 + 400);
+        assert_eq!(bob.rewards_pending, 400 + 200);
+        assert_eq!(project.total_rewards_accrued, alice.rewards_pending + bob.rewards_pending);
+    }
+
+    #[test]
+    fn fixed_pool_capacity_tracks_funded_rewards() {
+        let mut project = Project { rate_mode: 0, reward_rate_per_second: 1_000_000, ..variable_pool() };
+        project.total_rewards_deposited = 1_000;
+
+        // 1000 tokens earn 1 per second for 1000 seconds
+        assert_eq!(fixed_pool_capacity(&project, 0).unwrap(), 1_000);
+
+        let mut stake = Stake::default();
+        stake_at(&mut project, &mut stake, 600, 0);
+        assert_eq!(project.projected_liability, 600);
+        require_rewards_funded(&project).unwrap();
+        assert_eq!(fixed_pool_capacity(&project, 0).unwrap(), 400);
+
+        // Time passing moves liability from future to accrued without changing it
+        accrue_reward(&mut project, &mut stake, 500).unwrap();
+        assert_eq!(projected_liability(&project).unwrap(), 600);
+        assert_eq!(fixed_pool_capacity(&project, 500).unwrap(), 800);
+
+        stake_at(&mut project, &mut stake, 802, 500);
+        assert!(require_rewards_funded(&project).is_err());
+    }
+
+    #[test]
+    fn fixed_rewards_follow_reward_mint_decimals() {
+        // 1000 staking units earn 1 staking unit's worth per second over 1000 seconds
+        for (token_decimals, reward_decimals, earned) in [(6, 9, 1_000_000), (9, 6, 1), (6, 6, 1_000)] {
+            let mut project = Project {
+                rate_mode: 0,
+                reward_rate_per_second: 1_000_000,
+                token_decimals,
+                reward_decimals,
+                ..variable_pool()
+            };
+            project.total_rewards_deposited = earned;
+
+            // Funding is in reward units, capacity in staking units
+            assert_eq!(fixed_pool_capacity(&project, 0).unwrap(), 1_000);
+
+            let mut stake = Stake::default();
+            stake_at(&mut project, &mut stake, 1_000, 0);
+            assert_eq!(project.projected_liability, earned);
+            require_rewards_funded(&project).unwrap();
+            assert_eq!(fixed_pool_capacity(&project, 0).unwrap(), 0);
+
+            accrue_reward(&mut project, &mut stake, END).unwrap();
+            assert_eq!(stake.rewards_pending, earned);
+            assert_eq!(project.total_rewards_accrued, earned);
+        }
+    }
+
+    #[test]
+    fn early_deposits_earn_from_pool_start() {
+        let mut project = Project { pool_start_time: 200, ..variable_pool() };
+        let mut stake = Stake::default();
+
+        stake_at(&mut project, &mut stake, 8, 0);
+        accrue_reward(&mut project, &mut stake, 100).unwrap();
+        assert_eq!(stake.rewards_pending, 0);
+
+        accrue_reward(&mut project, &mut stake, END).unwrap();
+        assert_eq!(stake.rewards_pending, RATE * (END - 200) as u64);
+
+        let mut project = Project { rate_mode: 0, reward_rate_per_second: 1_000_000, pool_start_time: 200, ..variable_pool() };
+        let mut stake = Stake::default();
+
+        stake_at(&mut project, &mut stake, 1_000, 0);
+        assert_eq!(project.projected_liability, 800);
+        accrue_reward(&mut project, &mut stake, END).unwrap();
+        assert_eq!(stake.rewards_pending, 800);
+    }
+
+    #[test]
+    fn extending_variable_pool_spreads_remaining_rewards() {
+        let mut project = variable_pool();
+        project.total_rewards_deposited = RATE * END as u64;
+        let mut stake = Stake::default();
+
+        stake_at(&mut project, &mut stake, 8, 0);
+        reschedule_pool_end(&mut project, 1_500, 500).unwrap();
+        assert_eq!(project.reward_rate_per_second, RATE / 2);
+
+        accrue_reward(&mut project, &mut stake, 5_000).unwrap();
+        assert_eq!(stake.rewards_pending, project.total_rewards_deposited);
+        assert!(reschedule_pool_end(&mut project, 6_000, 5_000).is_err());
+    }
+
+    #[test]
+    fn fixed_pool_cannot_extend_past_funding() {
+        let mut project = Project { rate_mode: 0, reward_rate_per_second: 1_000_000, ..variable_pool() };
+        project.total_rewards_deposited = 1_500;
+        let mut stake = Stake::default();
+
+        stake_at(&mut project, &mut stake, 1_000, 0);
+        assert!(reschedule_pool_end(&mut project, 400, 500).is_err());
+        assert!(reschedule_pool_end(&mut project, 1_600, 500).is_err());
+        reschedule_pool_end(&mut project, 1_500, 500).unwrap();
+        assert_eq!(project.projected_liability, 1_500);
+    }
+
+    #[test]
+    fn boosted_tier_earns_weighted_share() {
+        let mut project = variable_pool();
+        project.lock_tiers[0] = LockTier { lockup_seconds: 0, multiplier_bps: BASE_MULTIPLIER_BPS };
+        project.lock_tiers[1] = LockTier { lockup_seconds: 100, multiplier_bps: 3 * BASE_MULTIPLIER_BPS };
+        project.lock_tier_count = 2;
+        let mut alice = Stake::default();
+        let mut bob = Stake { lock_tier: 1, ..Stake::default() };
+
+        stake_at(&mut project, &mut alice, 100, 0);
+        stake_at(&mut project, &mut bob, 100, 0);
+        assert_eq!(project.total_weight, 400);
+        accrue_reward(&mut project, &mut alice, END).unwrap();
+        accrue_reward(&mut project, &mut bob, END).unwrap();
+
+        assert_eq!(alice.rewards_pending, RATE * END as u64 / 4);
+        assert_eq!(bob.rewards_pending, RATE * END as u64 * 3 / 4);
+
+        unstake_at(&mut project, &mut bob, 50, END);
+        assert_eq!(bob.weight, 150);
+        assert_eq!(project.total_weight, 250);
+    }
+
+    #[test]
+    fn lots_unlock_independently_and_withdraw_fifo() {
+        let project = Project { lock_mode: LOCK_MODE_LOTS, ..variable_pool() };
+        let mut stake = Stake { amount: 600, ..Stake::default() };
+        lock_deposit(&project, &mut stake, 100, 100).unwrap();
+        lock_deposit(&project, &mut stake, 200, 200).unwrap();
+        lock_deposit(&project, &mut stake, 300, 300).unwrap();
+
+        assert_eq!(unlocked_amount(&project, &stake, 250).unwrap(), 300);
+        assert!(require_unlocked(&project, &stake, 301, 250).is_err());
+
+        consume_lots(&mut stake, 150, 250);
+        assert_eq!(stake.lot_count, 2);
+        assert_eq!(stake.lots[0].amount, 150);
+        assert_eq!(stake.lots[1].unlock_time, 300);
+
+        let project = Project { lock_mode: LOCK_MODE_WEIGHTED_AVERAGE, ..variable_pool() };
+        let mut stake = Stake::default();
+        lock_deposit(&project, &mut stake, 300, 100).unwrap();
+        lock_deposit(&project, &mut stake, 100, 500).unwrap();
+        assert_eq!(stake.lot_count, 1);
+        assert_eq!(stake.lots[0].unlock_time, 200);
+    }
+
+    #[test]
+    fn tier_upgrade_relocks_existing_lots() {
+        let mut project = Project { lock_mode: LOCK_MODE_LOTS, ..variable_pool() };
+        project.lock_tiers[0] = LockTier { lockup_seconds: 100, multiplier_bps: BASE_MULTIPLIER_BPS };
+        project.lock_tiers[1] = LockTier { lockup_seconds: 1_000, multiplier_bps: 2 * BASE_MULTIPLIER_BPS };
+        project.lock_tier_count = 2;
+        let mut stake = Stake::default();
+        stake_at(&mut project, &mut stake, 100, 0);
+        lock_deposit(&project, &mut stake, 100, 100).unwrap();
+
+        // Upgrading at t=50 boosts the old principal, so it takes the longer lock as well
+        switch_lock_tier(&project, &mut stake, 1, 50).unwrap();
+        stake_at(&mut project, &mut stake, 10, 50);
+        lock_deposit(&project, &mut stake, 10, 1_050).unwrap();
+        assert_eq!(stake.weight, 220);
+        assert_eq!(unlocked_amount(&project, &stake, 200).unwrap(), 0);
+        assert_eq!(unlocked_amount(&project, &stake, 1_050).unwrap(), 110);
+        assert!(switch_lock_tier(&project, &mut stake, 0, 1_100).is_err());
+
+        project.lock_mode = LOCK_MODE_WEIGHTED_AVERAGE;
+        let mut stake = Stake::default();
+        lock_deposit(&project, &mut stake, 100, 100).unwrap();
+        switch_lock_tier(&project, &mut stake, 1, 50).unwrap();
+        lock_deposit(&project, &mut stake, 10, 1_050).unwrap();
+        assert_eq!(stake.lots[0].unlock_time, 1_050);
+    }
+
+    #[test]
+    fn unbonding_stops_earning_until_completed_or_cancelled() {
+        let mut project = Project { lockup_seconds: 50, unbonding_seconds: 100, ..variable_pool() };
+        let mut alice = Stake::default();
+        let mut bob = Stake::default();
+        stake_at(&mut project, &mut alice, 100, 0);
+        stake_at(&mut project, &mut bob, 100, 0);
+
+        // Locked principal cannot be queued
+        accrue_reward(&mut project, &mut alice, 20).unwrap();
+        assert!(queue_unbonding(&mut project, &mut alice, 100, 20).is_err());
+
+        accrue_reward(&mut project, &mut alice, 200).unwrap();
+        assert_eq!(queue_unbonding(&mut project, &mut alice, 60, 200).unwrap(), 300);
+        assert_eq!(alice.weight, 40);
+        assert_eq!(project.total_weight, 140);
+        assert_eq!(project.total_staked, 140);
+        assert_eq!(project.total_unbonding, 60);
+
+        // Cancelling puts the full amount back to work at once
+        accrue_reward(&mut project, &mut alice, 250).unwrap();
+        assert_eq!(restake_unbonding(&mut project, &mut alice, 250).unwrap(), 60);
+        assert_eq!(alice.weight, 100);
+        assert_eq!(project.total_weight, 200);
+        assert_eq!(project.total_unbonding, 0);
+        assert!(restake_unbonding(&mut project, &mut alice, 250).is_err());
+
+        // A full request leaves the rewards earned so far pending
+        accrue_reward(&mut project, &mut alice, 300).unwrap();
+        let earned = alice.rewards_pending;
+        assert_eq!(queue_unbonding(&mut project, &mut alice, 100, 300).unwrap(), 400);
+        assert_eq!(alice.amount, 0);
+        accrue_reward(&mut project, &mut alice, 400).unwrap();
+        assert_eq!(alice.rewards_pending, earned);
+
+        // Lengthening the period later does not move a queued request
+        project.unbonding_seconds = MAX_UNBONDING_SECONDS;
+        assert!(take_unbonded(&mut project, &mut alice, 399).is_err());
+        assert_eq!(take_unbonded(&mut project, &mut alice, 400).unwrap(), 100);
+        assert_eq!(alice.unbonding_amount, 0);
+        assert_eq!(project.total_unbonding, 0);
+        assert!(take_unbonded(&mut project, &mut alice, 400).is_err());
+
+        // Bob held 100/140 of the weight during the partial request and the whole
+        // pool while Alice was fully unbonding
+        accrue_reward(&mut project, &mut bob, 400).unwrap();
+        assert_eq!(bob.rewards_pending, 200 * RATE / 2 + 50 * RATE * 10 / 14 + 50 * RATE / 2 + 100 * RATE);
+    }
+
+    #[test]
+    fn early_exit_penalty_decays_and_is_redistributed() {
+        let mut project = Project {
+            lock_mode: LOCK_MODE_LOTS,
+            lockup_seconds: 100,
+            early_exit_penalty_bps: 1_000,
+            early_exit_penalty_decays: true,
+            ..variable_pool()
+        };
+        let mut stake = Stake { amount: 2_000, ..Stake::default() };
+        lock_deposit(&project, &mut stake, 1_000, 50).unwrap();
+        lock_deposit(&project, &mut stake, 1_000, 100).unwrap();
+
+        // At t=50 the first lot is free; the second has half its lock left (5%)
+        assert_eq!(early_exit_penalty(&project, &stake, 1_000, 50).unwrap(), 0);
+        assert_eq!(early_exit_penalty(&project, &stake, 1_400, 50).unwrap(), 20);
+
+        project.early_exit_penalty_bps = 0;
+        assert!(early_exit_penalty(&project, &stake, 1_400, 50).is_err());
+
+        // Redistributed penalties reach remaining stakers in fixed pools too
+        let mut project = Project { rate_mode: 0, ..variable_pool() };
+        let mut alice = Stake::default();
+        stake_at(&mut project, &mut alice, 100, 0);
+        credit_penalty(&mut project, 40, true, 0).unwrap();
+        accrue_reward(&mut project, &mut alice, 0).unwrap();
+        assert_eq!(alice.rewards_pending, 40);
+        assert_eq!(project.total_rewards_deposited, 40);
+    }
+
+    #[test]
+    fn reward_vault_penalties_are_spread_not_swept() {
+        let mut project = variable_pool();
+        project.total_rewards_deposited = RATE * END as u64;
+        let mut stake = Stake::default();
+        stake_at(&mut project, &mut stake, 100, 0);
+
+        // A penalty half-way funds the rest of the pool instead of becoming surplus
+        accrue_reward(&mut project, &mut stake, 500).unwrap();
+        credit_penalty(&mut project, 500 * RATE, false, 500).unwrap();
+        assert_eq!(project.reward_rate_per_second, 2 * RATE);
+        assert_eq!(project.projected_liability, project.total_rewards_deposited);
+
+        accrue_reward(&mut project, &mut stake, END).unwrap();
+        assert_eq!(stake.rewards_pending, project.total_rewards_deposited);
+    }
+
+    #[test]
+    fn partial_withdraw_does_not_recount_fixed_rewards() {
+        // 1e6 / 1e9 per second: 1000 staked earns 1 per second
+        let mut project = Project { rate_mode: 0, reward_rate_per_second: 1_000_000, ..variable_pool() };
+        let mut stake = Stake::default();
+
+        stake_at(&mut project, &mut stake, 1_000, 0);
+        unstake_at(&mut project, &mut stake, 500, 100);
+        accrue_reward(&mut project, &mut stake, 200).unwrap();
+
+        assert_eq!(stake.rewards_pending, 100 + 50);
+        assert_eq!(stake.last_stake_timestamp, 0);
+    }
+
+    #[test]
+    fn role_grants_are_scoped_and_revocable() {
+        let pauser = Pubkey::new_unique();
+        let funder = Pubkey::new_unique();
+        let mut registry = RoleRegistry { target: Pubkey::default(), members: vec![], bump: 0 };
+
+        // Fee managers are platform-only, reward funders project-only, one role per grant
+        assert!(require_grantable_role(ROLE_FEE_MANAGER, PLATFORM_ROLES).is_ok());
+        assert!(require_grantable_role(ROLE_FEE_MANAGER, PROJECT_ROLES).is_err());
+        assert!(require_grantable_role(ROLE_REWARD_FUNDER, PLATFORM_ROLES).is_err());
+        assert!(require_grantable_role(ROLE_PAUSER | ROLE_REWARD_FUNDER, PROJECT_ROLES).is_err());
+        assert!(require_grantable_role(0, PROJECT_ROLES).is_err());
+
+        registry.grant(pauser, ROLE_PAUSER).unwrap();
+        registry.grant(funder, ROLE_REWARD_FUNDER).unwrap();
+        assert!(has_role(Some(&registry), &pauser, ROLE_PAUSER));
+        assert!(!has_role(Some(&registry), &pauser, ROLE_REWARD_FUNDER));
+        assert!(!has_role(None, &pauser, ROLE_PAUSER));
+
+        // Revoking one role keeps the member's others; revoking a missing role fails
+        registry.grant(pauser, ROLE_REWARD_FUNDER).unwrap();
+        registry.revoke(&pauser, ROLE_PAUSER).unwrap();
+        assert!(!has_role(Some(&registry), &pauser, ROLE_PAUSER));
+        assert!(has_role(Some(&registry), &pauser, ROLE_REWARD_FUNDER));
+        assert!(registry.revoke(&pauser, ROLE_PAUSER).is_err());
+        registry.revoke(&pauser, ROLE_REWARD_FUNDER).unwrap();
+        assert_eq!(registry.members.len(), 1);
+
+        while registry.members.len() < MAX_ROLE_MEMBERS {
+            registry.grant(Pubkey::new_unique(), ROLE_PAUSER).unwrap();
+        }
+        assert!(registry.grant(Pubkey::new_unique(), ROLE_PAUSER).is_err());
+        registry.grant(funder, ROLE_PAUSER).unwrap();
+    }
+
+    #[test]
+    fn fee_changes_are_capped_and_timelocked() {
+        assert!(validate_fees(MAX_PLATFORM_TOKEN_FEE_BPS, MAX_PLATFORM_SOL_FEE).is_ok());
+        assert!(validate_fees(MAX_PLATFORM_TOKEN_FEE_BPS + 1, 0).is_err());
+        assert!(validate_fees(0, MAX_PLATFORM_SOL_FEE + 1).is_err());
+
+        let pending = PendingFeeChange {
+            platform_token_fee_bps: 100,
+            platform_sol_fee: 0,
+            effective_time: fee_change_effective_time(1_000).unwrap(),
+            proposer: Pubkey::default(),
+            bump: 0,
+        };
+        assert_eq!(pending.effective_time, 1_000 + FEE_CHANGE_DELAY_SECONDS);
+        assert!(require_fee_change_due(&pending, 1_000).is_err());
+        assert!(require_fee_change_due(&pending, pending.effective_time - 1).is_err());
+        assert!(require_fee_change_due(&pending, pending.effective_time).is_ok());
+        assert!(fee_change_effective_time(i64::MAX).is_err());
+    }
+
+    #[test]
+    fn pausers_can_set_but_not_clear_pause_flags() {
+        let mut project = variable_pool();
+
+        apply_pause_flags(&mut project, PAUSE_DEPOSIT | PAUSE_CLAIM, true, false).unwrap();
+        assert!(project.deposit_paused && project.claim_paused && !project.withdraw_paused);
+
+        assert!(apply_pause_flags(&mut project, PAUSE_DEPOSIT, false, false).is_err());
+        assert!(project.deposit_paused);
+        assert!(apply_pause_flags(&mut project, 0, true, true).is_err());
+        assert!(apply_pause_flags(&mut project, PAUSE_REFLECTIONS, true, true).is_err());
+
+        apply_pause_flags(&mut project, PAUSE_DEPOSIT, false, true).unwrap();
+        assert!(!project.deposit_paused && project.claim_paused);
+    }
+
+    #[test]
+    fn guardians_can_only_add_global_pause_flags() {
+        let paused = next_global_pause(0, PAUSE_WITHDRAW, false).unwrap();
+        let paused = next_global_pause(paused, paused | PAUSE_REFLECTIONS, false).unwrap();
+        assert_eq!(paused, PAUSE_WITHDRAW | PAUSE_REFLECTIONS);
+
+        // Dropping any current flag, even while adding another, needs the admin
+        assert!(next_global_pause(paused, PAUSE_REFLECTIONS, false).is_err());
+        assert!(next_global_pause(paused, PAUSE_DEPOSIT, false).is_err());
+        assert!(next_global_pause(paused, 1 << 4, true).is_err());
+        assert_eq!(next_global_pause(paused, 0, true).unwrap(), 0);
+    }
+
+    #[test]
+    fn emergency_withdraw_pays_all_principal_and_drops_rewards() {
+        let mut project = variable_pool();
+        project.total_rewards_deposited = RATE * END as u64;
+        let mut alice = Stake::default();
+        let mut bob = Stake::default();
+
+        stake_at(&mut project, &mut alice, 300, 0);
+        stake_at(&mut project, &mut bob, 100, 0);
+        accrue_reward(&mut project, &mut alice, 200).unwrap();
+        queue_unbonding(&mut project, &mut alice, 200, 200).unwrap();
+
+        // Alice earned 3/4 of 200s, then half of the next 200s on what is still staked
+        let (forfeited, _) = forfeit_stake_rewards(&mut project, &mut alice, 400);
+        assert_eq!(forfeited, 200 * RATE * 3 / 4 + 200 * RATE / 2);
+        assert_eq!(release_stake_principal(&mut project, &mut alice, 400).unwrap(), 300);
+        assert_eq!((alice.amount, alice.unbonding_amount), (0, 0));
+        assert_eq!((project.total_staked, project.total_unbonding), (100, 0));
+
+        // Bob keeps his share and the forfeited rewards are no longer owed
+        accrue_reward(&mut project, &mut bob, END).unwrap();
+        assert_eq!(project.total_rewards_pending, bob.rewards_pending);
+        assert_eq!(project.total_rewards_accrued, bob.rewards_pending);
+    }
+
+    #[test]
+    fn returned_stake_keeps_earned_rewards() {
+        let mut project = variable_pool();
+        project.total_rewards_deposited = RATE * END as u64;
+        let mut alice = Stake::default();
+        let mut bob = Stake::default();
+
+        stake_at(&mut project, &mut alice, 100, 0);
+        stake_at(&mut project, &mut bob, 100, 0);
+        accrue_reward(&mut project, &mut alice, 100).unwrap();
+        queue_unbonding(&mut project, &mut alice, 40, 100).unwrap();
+
+        // Mirrors return_stake_principal: settle first, then hand back everything
+        accrue_reward(&mut project, &mut alice, 400).unwrap();
+        assert_eq!(release_stake_principal(&mut project, &mut alice, 400).unwrap(), 100);
+        assert_eq!((project.total_staked, project.total_unbonding), (100, 0));
+
+        // Rewards earned until the return stay claimable, and nothing accrues after it
+        let earned = 100 * RATE / 2 + 300 * RATE * 60 / 160;
+        assert_eq!(alice.rewards_pending, earned);
+        accrue_reward(&mut project, &mut alice, END).unwrap();
+        assert_eq!(alice.rewards_pending, earned);
+        accrue_reward(&mut project, &mut bob, END).unwrap();
+        assert_eq!(project.total_rewards_pending, alice.rewards_pending + bob.rewards_pending);
+    }
+
+    #[test]
+    fn project_closes_only_when_ended_and_settled() {
+        let mut project = variable_pool();
+        let mut stake = Stake::default();
+
+        stake_at(&mut project, &mut stake, 100, 0);
+        assert!(require_project_closable(&project, END - 1).is_err());
+        assert!(require_project_closable(&project, END).is_err());
+
+        accrue_reward(&mut project, &mut stake, END).unwrap();
+        queue_unbonding(&mut project, &mut stake, 100, END).unwrap();
+        assert!(require_project_closable(&project, END).is_err());
+        release_unbonding(&mut project, &mut stake);
+
+        // Principal is gone but the earned rewards are still owed
+        assert!(require_project_closable(&project, END).is_err());
+        project.total_rewards_pending = 0;
+        project.total_reflections_pending = 1;
+        assert!(require_project_closable(&project, END).is_err());
+        project.total_reflections_pending = 0;
+        assert!(require_project_closable(&project, END).is_ok());
+    }
+
+    #[test]
+    fn compound_relocks_only_when_configured() {
+        for resets_lock in [false, true] {
+            let mut project = Project { lockup_seconds: 100, compound_resets_lock: resets_lock, ..variable_pool() };
+            let mut stake = Stake::default();
+            stake_at(&mut project, &mut stake, 100, 0);
+            accrue_reward(&mut project, &mut stake, 300).unwrap();
+
+            let rewards = stake.rewards_pending;
+            compound_rewards(&mut project, &mut stake, rewards, 50, 300).unwrap();
+            assert_eq!((stake.amount, stake.rewards_pending), (150, 0));
+            assert_eq!((project.total_staked, project.total_rewards_pending), (150, 0));
+            assert_eq!(project.total_rewards_claimed, rewards);
+
+            let unlocked = if resets_lock { 0 } else { 150 };
+            assert_eq!(unlocked_amount(&project, &stake, 300).unwrap(), unlocked);
+            assert_eq!(unlocked_amount(&project, &stake, 400).unwrap(), 150);
+        }
+
+        // Per-lot pools: the compounded amount joins the newest lot unless the lock resets
+        for (resets_lock, unlock_time) in [(false, 100), (true, 150)] {
+            let mut project = Project {
+                lock_mode: LOCK_MODE_LOTS,
+                lockup_seconds: 100,
+                compound_resets_lock: resets_lock,
+                ..variable_pool()
+            };
+            let mut stake = Stake::default();
+            stake_at(&mut project, &mut stake, 100, 0);
+            lock_deposit(&project, &mut stake, 100, 100).unwrap();
+            accrue_reward(&mut project, &mut stake, 50).unwrap();
+
+            let rewards = stake.rewards_pending;
+            compound_rewards(&mut project, &mut stake, rewards, 20, 50).unwrap();
+            assert_eq!(stake.lots[stake.lot_count as usize - 1].unlock_time, unlock_time);
+            assert_eq!(unlocked_amount(&project, &stake, unlock_time).unwrap(), 120);
+        }
+    }
+
+    #[test]
+    fn payouts_never_exceed_deposited_rewards() {
+        const STAKERS: usize = 6;
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |bound: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % bound
+        };
+
+        for _ in 0..200 {
+            let rate = 1 + next(1_000_000);
+            let deposited = rate * END as u64;
+            let mut project = Project { reward_rate_per_second: rate, ..variable_pool() };
+            let mut stakes: Vec<Stake> = (0..STAKERS).map(|_| Stake::default()).collect();
+
+            let mut now = 0;
+            while now < END + 100 {
+                now += 1 + next(60) as i64;
+                let stake = &mut stakes[next(STAKERS as u64) as usize];
+                if stake.amount > 0 && next(3) == 0 {
+                    let amount = 1 + next(stake.amount);
+                    unstake_at(&mut project, stake, amount, now);
+                } else {
+                    stake_at(&mut project, stake, 1 + next(1_000_000_000), now);
+                }
+            }
+
+            let mut paid = 0u64;
+            for stake in stakes.iter_mut() {
+                accrue_reward(&mut project, stake, now).unwrap();
+                paid += stake.rewards_pending;
+            }
+
+            assert!(paid <= project.total_rewards_accrued);
+            assert!(project.total_rewards_accrued <= deposited);
+            // Only rounding dust (< 1 unit per settlement) may be left unpaid
+            assert!(project.total_rewards_accrued - paid <= 1_000);
+        }
+    }
+}